@@ -0,0 +1,205 @@
+//! What every game server checker has in common: the [`HealthCheck`] trait,
+//! the [`ProbeOutcome`] taxonomy of ways a server can look offline, and the
+//! [`CheckResult`] a check reports.
+//!
+//! Each checker keeps its own richer result (world details, QUIC replies,
+//! ...) and reduces it to a [`CheckResult`], so the monitor treats every game
+//! the same way.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub use async_trait::async_trait;
+
+/// A configured check of one game server.
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    /// Name of the service, as shown on the dashboard (e.g. `"Terraria"`).
+    fn name(&self) -> &str;
+
+    /// Checks the server once. Never fails: every way the server can be
+    /// unreachable is reported through [`CheckResult::outcome`].
+    async fn check(&self) -> CheckResult;
+}
+
+/// Result of a check at the network level, separating the ways a server can look offline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeOutcome {
+    /// The game server replied to the probe.
+    Answered,
+    /// The host name did not resolve, or resolved to no address we may use.
+    DnsFailure(String),
+    /// The host is up but nothing listens on the port (e.g. the container is down).
+    Refused,
+    /// Nothing came back in time: packets dropped on the way (firewall, broken
+    /// port forward) or a server that accepts connections but hangs.
+    Timeout,
+    /// The connection was reset or closed before the server replied.
+    Reset,
+    /// No route to the host or its network.
+    Unreachable(String),
+    /// The server replied with something that is not a reply of its game protocol.
+    ProtocolError(String),
+}
+
+impl ProbeOutcome {
+    /// Classifies the error of a socket operation.
+    pub fn from_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused => ProbeOutcome::Refused,
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ProbeOutcome::Timeout,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => ProbeOutcome::Reset,
+            io::ErrorKind::InvalidData => ProbeOutcome::ProtocolError(error.to_string()),
+            _ => ProbeOutcome::Unreachable(error.to_string()),
+        }
+    }
+
+    /// Whether the outcome says something about the server. A failed DNS
+    /// lookup may just as well be our own resolver's fault, so it is No Data
+    /// rather than offline.
+    pub fn is_conclusive(&self) -> bool {
+        !matches!(self, ProbeOutcome::DnsFailure(_))
+    }
+}
+
+impl fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeOutcome::Answered => write!(f, "Answered"),
+            ProbeOutcome::DnsFailure(reason) => write!(f, "DNS failure: {}", reason),
+            ProbeOutcome::Refused => write!(f, "Connection refused"),
+            ProbeOutcome::Timeout => write!(f, "Timed out"),
+            ProbeOutcome::Reset => write!(f, "Connection reset"),
+            ProbeOutcome::Unreachable(reason) => write!(f, "Unreachable: {}", reason),
+            ProbeOutcome::ProtocolError(reason) => write!(f, "Protocol error: {}", reason),
+        }
+    }
+}
+
+/// A probe that got no usable reply, for checkers that report failures as
+/// errors. The message says where it failed; the outcome says how.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeError {
+    pub outcome: ProbeOutcome,
+    pub message: String,
+}
+
+impl ProbeError {
+    pub fn new(outcome: ProbeOutcome, message: impl Into<String>) -> Self {
+        Self { outcome, message: message.into() }
+    }
+}
+
+impl From<io::Error> for ProbeError {
+    fn from(error: io::Error) -> Self {
+        Self::new(ProbeOutcome::from_io_error(&error), error.to_string())
+    }
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ProbeError {}
+
+/// What a [`HealthCheck`] found, common to every game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub is_online: bool,
+    pub latency_ms: u128,
+    pub outcome: ProbeOutcome,
+    /// Why the server counts as offline, when it does: the outcome, or what
+    /// was wrong with a reply that came back (e.g. a version mismatch).
+    pub failure: Option<String>,
+    /// Game version the server runs, when the check revealed it.
+    pub version: Option<String>,
+}
+
+impl CheckResult {
+    /// Result of a check that got no reply.
+    pub fn failed(outcome: ProbeOutcome, failure: impl Into<String>) -> Self {
+        Self { is_online: false, latency_ms: 0, outcome, failure: Some(failure.into()), version: None }
+    }
+
+    /// Online as far as the dashboard is concerned: `None` (No Data) when the
+    /// outcome says nothing about the server.
+    pub fn status(&self) -> Option<bool> {
+        self.outcome.is_conclusive().then_some(self.is_online)
+    }
+}
+
+impl From<ProbeError> for CheckResult {
+    fn from(error: ProbeError) -> Self {
+        CheckResult::failed(error.outcome, error.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(CheckResult);
+
+    #[async_trait]
+    impl HealthCheck for Fixed {
+        fn name(&self) -> &str {
+            "Fixed"
+        }
+
+        async fn check(&self) -> CheckResult {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn io_errors_are_classified() {
+        let outcome = |kind| ProbeOutcome::from_io_error(&io::Error::from(kind));
+
+        assert_eq!(outcome(io::ErrorKind::ConnectionRefused), ProbeOutcome::Refused);
+        assert_eq!(outcome(io::ErrorKind::TimedOut), ProbeOutcome::Timeout);
+        assert_eq!(outcome(io::ErrorKind::UnexpectedEof), ProbeOutcome::Reset);
+        assert!(matches!(outcome(io::ErrorKind::InvalidData), ProbeOutcome::ProtocolError(_)));
+        assert!(matches!(outcome(io::ErrorKind::NetworkUnreachable), ProbeOutcome::Unreachable(_)));
+    }
+
+    #[test]
+    fn dns_failure_is_no_data() {
+        let dns = CheckResult::failed(ProbeOutcome::DnsFailure("no such host".to_string()), "no such host");
+        let refused = CheckResult::failed(ProbeOutcome::Refused, "Connection refused");
+
+        assert_eq!(dns.status(), None);
+        assert_eq!(refused.status(), Some(false));
+    }
+
+    #[test]
+    fn probe_error_becomes_a_failed_result() {
+        let error = ProbeError::new(ProbeOutcome::Timeout, "Timeout connecting to 127.0.0.1:5520");
+        let result = CheckResult::from(error);
+
+        assert!(!result.is_online);
+        assert_eq!(result.outcome, ProbeOutcome::Timeout);
+        assert_eq!(result.failure.as_deref(), Some("Timeout connecting to 127.0.0.1:5520"));
+    }
+
+    #[tokio::test]
+    async fn checks_are_object_safe() {
+        let checks: Vec<Box<dyn HealthCheck>> = vec![Box::new(Fixed(CheckResult {
+            is_online: true,
+            latency_ms: 12,
+            outcome: ProbeOutcome::Answered,
+            failure: None,
+            version: Some("1.0".to_string()),
+        }))];
+
+        for check in &checks {
+            assert_eq!(check.name(), "Fixed");
+            assert_eq!(check.check().await.status(), Some(true));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Full QUIC handshake with quinn, to check the server's TLS setup end to end
+//! and read its certificate.
+//!
+//! The certificate is inspected, not validated: game servers commonly run on
+//! self-signed certificates, and what we want to know is when it expires.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use quinn::crypto::rustls::QuicClientConfig;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use tokio::time::timeout;
+
+/// Result of a completed QUIC handshake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandshakeInfo {
+    /// Time from sending the first Initial to the handshake completing.
+    pub handshake_ms: u128,
+    /// quinn's round-trip estimate at the end of the handshake.
+    pub rtt_ms: u128,
+    /// ALPN protocol the server picked, if any.
+    pub alpn: Option<String>,
+    pub certificate: Option<CertificateInfo>,
+}
+
+/// The server's leaf certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+}
+
+impl CertificateInfo {
+    /// Whole days until the certificate expires; negative once it has.
+    pub fn days_until_expiry(&self) -> i64 {
+        (self.not_after - Utc::now()).num_days()
+    }
+
+    fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).context("Invalid server certificate")?;
+        let time = |t: x509_parser::time::ASN1Time| {
+            DateTime::from_timestamp(t.timestamp(), 0).ok_or_else(|| anyhow!("Certificate date out of range"))
+        };
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            not_before: time(cert.validity().not_before)?,
+            not_after: time(cert.validity().not_after)?,
+        })
+    }
+}
+
+/// Completes a QUIC handshake with `addr`, presenting `server_name` as SNI
+/// and offering `alpn`. Fails if the server refuses the handshake or it does
+/// not finish within `deadline`.
+pub async fn perform_handshake(
+    addr: SocketAddr,
+    server_name: &str,
+    alpn: &[String],
+    deadline: Duration,
+) -> Result<HandshakeInfo> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let mut tls = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(InspectOnly(provider)))
+        .with_no_client_auth();
+    tls.alpn_protocols = alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+    let client_config = quinn::ClientConfig::new(Arc::new(QuicClientConfig::try_from(tls)?));
+
+    let bind_addr: SocketAddr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse()?;
+    let mut endpoint = quinn::Endpoint::client(bind_addr)?;
+    endpoint.set_default_client_config(client_config);
+
+    let start = Instant::now();
+    let connecting = endpoint.connect(addr, server_name)?;
+    let connection = timeout(deadline, connecting)
+        .await
+        .map_err(|_| anyhow!("Handshake with {} timed out", addr))?
+        .with_context(|| format!("Handshake with {} failed", addr))?;
+    let handshake_ms = start.elapsed().as_millis();
+
+    let alpn = connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.protocol)
+        .map(|protocol| String::from_utf8_lossy(&protocol).into_owned());
+    let certificate = connection
+        .peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+        .and_then(|chain| chain.first().map(|leaf| CertificateInfo::from_der(leaf)))
+        .transpose()?;
+
+    let info = HandshakeInfo { handshake_ms, rtt_ms: connection.rtt().as_millis(), alpn, certificate };
+
+    connection.close(0u32.into(), b"health check");
+    // Give the close a moment to go out, without waiting for the full drain period
+    let _ = timeout(Duration::from_millis(100), endpoint.wait_idle()).await;
+
+    Ok(info)
+}
+
+/// Accepts any certificate, but still checks the handshake signatures so the
+/// server has to own the certificate it presents.
+#[derive(Debug)]
+struct InspectOnly(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for InspectOnly {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -1,156 +1,636 @@
+mod handshake;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod mtu;
+mod quic;
+mod tls;
+
+pub use handshake::{CertificateInfo, HandshakeInfo};
+pub use mtu::MtuReport;
+pub use health_check::{CheckResult, HealthCheck, ProbeError, ProbeOutcome};
+pub use probe_capture::Capture;
+pub use quic::{version_name, PacketType, QuicReply, QUIC_V1, QUIC_V2};
+pub use tls::{ClientHelloBuilder, TransportParameters};
+
+use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::net::UdpSocket;
-use tokio::time::timeout;
+use tokio::task::JoinSet;
+use tokio::time::{timeout_at, Instant};
 use anyhow::{Context, Result};
 use rand::Rng;
 
+/// How long to wait for replies after the first packet goes out, unless
+/// [`ProbeOptions::timeout`] says otherwise.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Gap between the packets of a burst, so they don't queue behind each other.
+const BURST_SPACING: Duration = Duration::from_millis(20);
+
 pub struct ServerInfo {
+    /// The server answered in a QUIC version we speak.
     pub is_online: bool,
+    /// Average round trip of the answered packets.
     pub latency_ms: u128,
+    /// Loss and round-trip spread over the burst.
+    pub stats: ProbeStats,
+    /// Header of the first packet the server answered with: packet type,
+    /// versions and the server's connection ID.
+    pub reply: QuicReply,
+    /// How the server answered the Initial resent with its Retry token, when
+    /// it answered the probe with a Retry.
+    pub retry: Option<RetryInfo>,
+    /// Outcome of the full handshake, for deep checks only.
+    pub handshake: Option<HandshakeInfo>,
+    /// Why the deep check's handshake failed, when it did.
+    pub handshake_error: Option<String>,
+}
+
+/// A Retry from the server and the Initial resent with its token.
+///
+/// Servers send Retry to validate client addresses before committing state,
+/// which they typically switch on under load or attack, so a Retry is a sign
+/// the server is busy rather than a failure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryInfo {
+    /// Round trip to the Retry.
+    pub retry_rtt_ms: u128,
+    /// Round trip of the resent Initial, if the server answered it.
+    pub final_rtt_ms: Option<u128>,
+    /// Header of the server's answer to the resent Initial.
+    pub final_reply: Option<QuicReply>,
+    /// Length of the address validation token.
+    pub token_len: usize,
+}
+
+/// Packet loss and round trips over the packets of one burst.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProbeStats {
+    pub sent: usize,
+    pub received: usize,
+    pub loss_percent: f64,
+    pub min_rtt_ms: f64,
+    pub avg_rtt_ms: f64,
+    pub max_rtt_ms: f64,
+    /// Mean difference between the round trips of consecutive packets.
+    pub jitter_ms: f64,
+}
+
+impl ProbeStats {
+    /// Stats for `sent` packets, given the round trips of the answered ones
+    /// in the order they were sent.
+    fn from_rtts(sent: usize, rtts: &[Duration]) -> Self {
+        let ms: Vec<f64> = rtts.iter().map(|rtt| rtt.as_secs_f64() * 1000.0).collect();
+        let mut stats = ProbeStats {
+            sent,
+            received: ms.len(),
+            loss_percent: if sent == 0 { 0.0 } else { (sent - ms.len()) as f64 * 100.0 / sent as f64 },
+            ..Default::default()
+        };
+        if ms.is_empty() {
+            return stats;
+        }
+
+        stats.min_rtt_ms = ms.iter().copied().fold(f64::INFINITY, f64::min);
+        stats.max_rtt_ms = ms.iter().copied().fold(0.0, f64::max);
+        stats.avg_rtt_ms = ms.iter().sum::<f64>() / ms.len() as f64;
+        if ms.len() > 1 {
+            let deltas: f64 = ms.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum();
+            stats.jitter_ms = deltas / (ms.len() - 1) as f64;
+        }
+        stats
+    }
+}
+
+/// Result of probing one of the addresses a host name resolved to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressResult {
+    pub address: SocketAddr,
+    pub is_online: bool,
+    pub latency_ms: u128,
+    /// Loss and round-trip spread over the burst, if the address answered.
+    pub stats: Option<ProbeStats>,
+    /// What the address answered with, if it answered.
+    pub reply: Option<QuicReply>,
+    /// The follow-up to a Retry, if the address answered with one.
+    pub retry: Option<RetryInfo>,
+    /// Why the address did not answer, when it didn't.
+    pub error: Option<String>,
+}
+
+impl AddressResult {
+    /// `"IPv4"` or `"IPv6"`.
+    pub fn family(&self) -> &'static str {
+        if self.address.is_ipv4() { "IPv4" } else { "IPv6" }
+    }
+}
+
+/// What the probe sends to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProbeStrategy {
+    /// A full QUIC v1 Initial with a ClientHello. The server is online if it
+    /// answers in v1, which also exercises its TLS configuration.
+    #[default]
+    Initial,
+    /// A packet with a reserved greasing version, which any RFC 9000 server
+    /// must answer with Version Negotiation. Cheap and crypto-free; the server
+    /// is online if the versions it lists include v1.
+    VersionNegotiation,
+}
+
+impl ProbeStrategy {
+    /// Whether `reply` shows a server we could talk to.
+    fn accepts(self, reply: &QuicReply) -> bool {
+        match self {
+            ProbeStrategy::Initial => !reply.is_version_mismatch(),
+            ProbeStrategy::VersionNegotiation => {
+                reply.is_version_mismatch() && reply.supported_versions.contains(&QUIC_V1)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for ProbeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "initial" => Ok(ProbeStrategy::Initial),
+            "version-negotiation" | "vn" => Ok(ProbeStrategy::VersionNegotiation),
+            other => Err(format!("unknown probe strategy '{}' (expected 'initial' or 'version-negotiation')", other)),
+        }
+    }
+}
+
+/// How the probe presents itself to the server.
+#[derive(Debug, Clone, Default)]
+pub struct ProbeOptions {
+    pub strategy: ProbeStrategy,
+    /// Name sent as SNI with the Initial strategy; defaults to the host being checked.
+    pub server_name: Option<String>,
+    /// ALPN protocols to offer with the Initial strategy. Servers that require a specific one reject a hello without it.
+    pub alpn: Vec<String>,
+    /// Packets to send per address, each with its own connection IDs. The
+    /// server is online if any of them is answered, so a single lost datagram
+    /// doesn't fail the check. 0 is treated as 1.
+    pub burst: usize,
+    /// How long to wait for replies, and for the handshake of a deep check.
+    /// Defaults to [`PROBE_TIMEOUT`].
+    pub timeout: Option<Duration>,
+    /// Records every probe datagram sent and received, for Wireshark.
+    pub capture: Option<Capture>,
+}
+
+/// A [`ProbeOptions`] resolved for one host, cheap to hand to each address probe.
+#[derive(Clone)]
+struct Probe {
+    strategy: ProbeStrategy,
+    client_hello: ClientHelloBuilder,
+    burst: usize,
+    /// Size Initials are padded to.
+    datagram_size: usize,
+    /// Resend the Initial with the token when the server answers with a Retry.
+    follow_retry: bool,
+    timeout: Duration,
+    capture: Option<Capture>,
+}
+
+impl ProbeOptions {
+    fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(PROBE_TIMEOUT)
+    }
+
+    fn probe_for(&self, host: &str) -> Probe {
+        Probe {
+            strategy: self.strategy,
+            client_hello: ClientHelloBuilder::new()
+                .server_name(self.server_name.as_deref().unwrap_or(host))
+                .alpn(&self.alpn),
+            burst: self.burst.max(1),
+            datagram_size: quic::MIN_INITIAL_SIZE,
+            follow_retry: true,
+            timeout: self.timeout(),
+            capture: self.capture.clone(),
+        }
+    }
 }
 
 pub async fn check_hytale_status(host: &str, port: u16) -> Result<ServerInfo> {
-    let addr_str = format!("{}:{}", host, port);
-    let addrs = tokio::net::lookup_host(&addr_str).await
-        .context("Failed to resolve host address")?;
+    check_hytale_status_with_options(host, port, &ProbeOptions::default()).await
+}
+
+/// Same as [`check_hytale_status`], with the ClientHello configured by `options`.
+pub async fn check_hytale_status_with_options(host: &str, port: u16, options: &ProbeOptions) -> Result<ServerInfo> {
+    Ok(probe_first_answering(host, port, options).await?.1)
+}
 
-    let mut last_error = anyhow::anyhow!("No address resolved");
+/// Probes the resolved addresses in turn, returning the first that answered
+/// along with what it answered.
+async fn probe_first_answering(host: &str, port: u16, options: &ProbeOptions) -> Result<(SocketAddr, ServerInfo)> {
+    let addrs = resolve(host, port).await?;
+    let probe = options.probe_for(host);
+
+    let mut last_error = None;
 
     for addr in addrs {
-        // Bind to appropriate interface based on IP version
-        let bind_addr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
-
-        let socket = match UdpSocket::bind(bind_addr).await {
-            Ok(s) => s,
-            Err(e) => {
-                last_error = e.into();
-                continue;
+        match probe_address(&probe, addr).await {
+            Ok(AddressProbe { stats, reply, retry }) => {
+                let info = ServerInfo {
+                    is_online: probe.strategy.accepts(&reply),
+                    latency_ms: stats.avg_rtt_ms.round() as u128,
+                    stats,
+                    reply,
+                    retry,
+                    handshake: None,
+                    handshake_error: None,
+                };
+                return Ok((addr, info));
             }
-        };
-
-        // Create QUIC Initial Packet
-        let mut packet = vec![0u8; 1200]; // Allocate 1200 bytes (Min MTU for QUIC)
-        let mut offset = 0;
-
-        // 1. Header Byte (Long Header, Initial, PN Len=1)
-        packet[offset] = 0xC0;
-        offset += 1;
-
-        // 2. Version (4 bytes) - 0x00000001 (QUIC v1)
-        packet[offset] = 0x00;
-        packet[offset+1] = 0x00;
-        packet[offset+2] = 0x00;
-        packet[offset+3] = 0x01;
-        offset += 4;
-
-        // 3. Dest Connection ID Length (1 byte)
-        packet[offset] = 8;
-        offset += 1;
-
-        // 4. Dest Connection ID (8 bytes - random)
-        rand::rng().fill(&mut packet[offset..offset+8]);
-        offset += 8;
-
-        // 5. Source Connection ID Length (1 byte)
-        packet[offset] = 8;
-        offset += 1;
-
-        // 6. Source Connection ID (8 bytes - random)
-        rand::rng().fill(&mut packet[offset..offset+8]);
-        offset += 8;
-
-        // 7. Token Length (VarInt) - 0
-        packet[offset] = 0;
-        offset += 1;
-
-        // 8. Length (VarInt)
-        // Payload = Packet Num (1) + Frame Type (1) + Off (1) + Len (1) + Crypto Data (46) = 50 bytes
-        // But for simplicity, we mock the length field as provided: 0x4000 | 100
-        let payload_length: u16 = 100;
-        let len_field = 0x4000 | payload_length;
-        packet[offset] = (len_field >> 8) as u8;
-        packet[offset+1] = (len_field & 0xFF) as u8;
-        offset += 2;
-
-        // 9. Packet Number (1 byte)
-        packet[offset] = 0;
-        offset += 1;
-
-        // 10. Frame Type: CRYPTO (0x06)
-        packet[offset] = 0x06;
-        offset += 1;
-
-        // 11. Offset (VarInt) - 0
-        packet[offset] = 0;
-        offset += 1;
-
-        // 12. Length (VarInt) - 50 bytes of crypto data
-        let crypto_data_len: u8 = 50;
-        packet[offset] = crypto_data_len;
-        offset += 1;
-
-        // 13. CRYPTO Data (Simplified TLS ClientHello)
-        let client_hello_prefix = [
-            0x01, // Handshake Type: ClientHello
-            0x00, 0x00, 0x2e, // Length: 46 bytes
-            0x03, 0x03, // Version: TLS 1.2
-        ];
-
-        // Copy prefix
-        for b in client_hello_prefix.iter() {
-            packet[offset] = *b;
-            offset += 1;
+            Err(e) => last_error = Some(e),
         }
+    }
+
+    // resolve() never returns an empty list
+    Err(last_error.expect("at least one resolved address").into())
+}
+
+/// Probes the server like [`check_hytale_status_with_options`], then
+/// completes a real QUIC handshake with the address that answered, to time
+/// the handshake and read the server's certificate.
+///
+/// `latency_ms` stays the probe's round trip, averaged over the burst. The
+/// handshake is only attempted when the probe found the server online, and
+/// the server then only stays online if the handshake also succeeds; a failed
+/// handshake is reported in `handshake_error` rather than as an error, since
+/// the server did answer.
+pub async fn check_hytale_deep(host: &str, port: u16, options: &ProbeOptions) -> Result<ServerInfo> {
+    let (addr, mut info) = probe_first_answering(host, port, options).await?;
+    if !info.is_online {
+        return Ok(info);
+    }
 
-        // Random 32 bytes
-        rand::rng().fill(&mut packet[offset..offset+32]);
-        offset += 32;
-
-        // Suffix
-        let client_hello_suffix = [
-            0x00, // Session ID Length
-            0x00, 0x02, // Cipher Suites Length
-            0x13, 0x01, // TLS_AES_128_GCM_SHA256
-            0x01, // Compression Methods Length
-            0x00, // Compression Method: none
-        ];
-
-        for b in client_hello_suffix.iter() {
-            packet[offset] = *b;
-            offset += 1;
+    match check_hytale_handshake(host, addr, options).await {
+        Ok(handshake) => info.handshake = Some(handshake),
+        Err(e) => {
+            info.is_online = false;
+            info.handshake_error = Some(format!("{:#}", e));
         }
+    }
 
-        // 14. Padding (zeroes)
-        // The vector was initialized with zeroes, so just ensure we send exactly 1200 bytes
+    Ok(info)
+}
 
-        let start = std::time::Instant::now();
+/// Completes a QUIC handshake with `addr`, one of the addresses `host`
+/// resolved to, without probing it first. For callers that already know
+/// which address answered, e.g. from [`check_hytale_addresses`].
+pub async fn check_hytale_handshake(host: &str, addr: SocketAddr, options: &ProbeOptions) -> Result<HandshakeInfo> {
+    let server_name = options.server_name.as_deref().unwrap_or(host);
+    handshake::perform_handshake(addr, server_name, &options.alpn, options.timeout()).await
+}
 
-        if let Err(e) = socket.send_to(&packet, addr).await {
-            last_error = e.into();
-            continue;
+/// A Hytale server to check through the common [`HealthCheck`] interface.
+#[derive(Debug, Clone)]
+pub struct HytaleCheck {
+    pub host: String,
+    pub port: u16,
+    pub options: ProbeOptions,
+}
+
+impl HytaleCheck {
+    pub fn new(host: impl Into<String>, port: u16, options: ProbeOptions) -> Self {
+        Self { host: host.into(), port, options }
+    }
+}
+
+#[health_check::async_trait]
+impl HealthCheck for HytaleCheck {
+    fn name(&self) -> &str {
+        "Hytale"
+    }
+
+    async fn check(&self) -> CheckResult {
+        match check_hytale_status_with_options(&self.host, self.port, &self.options).await {
+            Ok(info) => CheckResult::from(&info),
+            Err(e) => CheckResult::failed(probe_outcome(&e), format!("{:#}", e)),
         }
+    }
+}
+
+impl From<&ServerInfo> for CheckResult {
+    fn from(info: &ServerInfo) -> Self {
+        // The server replied, but with a version we don't speak or a handshake that failed
+        let failure = match &info.handshake_error {
+            Some(error) => Some(error.clone()),
+            None => (!info.is_online).then(|| info.reply.to_string()),
+        };
+        let outcome = match &failure {
+            Some(failure) => ProbeOutcome::ProtocolError(failure.clone()),
+            None => ProbeOutcome::Answered,
+        };
+        CheckResult { is_online: info.is_online, latency_ms: info.latency_ms, outcome, failure, version: None }
+    }
+}
+
+/// Finds the largest Initial the path to the server carries, to tell a path
+/// that drops large UDP datagrams apart from a server that is down.
+///
+/// Probes the first resolved address only. Takes several seconds, since the
+/// search ends on a size that times out.
+pub async fn check_hytale_mtu(host: &str, port: u16, options: &ProbeOptions) -> Result<MtuReport> {
+    let addr = resolve(host, port).await?
+        .into_iter()
+        .next()
+        .context("No address resolved")?;
+    Ok(mtu::diagnose(&options.probe_for(host), addr).await)
+}
+
+/// Probes every resolved address concurrently, so a broken IPv6 path or a
+/// stale A record shows up next to the working one.
+///
+/// Results keep the resolver's order. Fails only if the host does not resolve.
+pub async fn check_hytale_addresses(host: &str, port: u16, options: &ProbeOptions) -> Result<Vec<AddressResult>> {
+    let addrs = resolve(host, port).await?;
+
+    let mut probes = JoinSet::new();
+    for (index, addr) in addrs.into_iter().enumerate() {
+        let probe = options.probe_for(host);
+        probes.spawn(async move {
+            let result = match probe_address(&probe, addr).await {
+                Ok(AddressProbe { stats, reply, retry }) => AddressResult {
+                    address: addr,
+                    is_online: probe.strategy.accepts(&reply),
+                    latency_ms: stats.avg_rtt_ms.round() as u128,
+                    stats: Some(stats),
+                    reply: Some(reply),
+                    retry,
+                    error: None,
+                },
+                Err(e) => AddressResult {
+                    address: addr,
+                    is_online: false,
+                    latency_ms: 0,
+                    stats: None,
+                    reply: None,
+                    retry: None,
+                    error: Some(e.to_string()),
+                },
+            };
+            (index, result)
+        });
+    }
+
+    let mut results = probes.join_all().await;
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+async fn resolve(host: &str, port: u16) -> std::result::Result<Vec<SocketAddr>, ProbeError> {
+    let addr_str = format!("{}:{}", host, port);
+    let dns_failure = |reason: String| {
+        ProbeError::new(ProbeOutcome::DnsFailure(reason.clone()), format!("Failed to resolve host address: {}", reason))
+    };
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(&addr_str).await
+        .map_err(|e| dns_failure(e.to_string()))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(dns_failure("No address resolved".to_string()));
+    }
+    Ok(addrs)
+}
+
+/// How the probe failed, for an error returned by one of the checks. Errors
+/// that don't come from the probe itself count as unreachable.
+pub fn probe_outcome(error: &anyhow::Error) -> ProbeOutcome {
+    match error.downcast_ref::<ProbeError>() {
+        Some(probe_error) => probe_error.outcome.clone(),
+        None => ProbeOutcome::Unreachable(format!("{:#}", error)),
+    }
+}
+
+/// What one address answered the probe with.
+struct AddressProbe {
+    stats: ProbeStats,
+    /// The first reply to arrive.
+    reply: QuicReply,
+    retry: Option<RetryInfo>,
+}
+
+/// One packet of a burst, with the connection IDs it was sent with.
+struct ProbePacket {
+    dcid: [u8; 8],
+    scid: [u8; 8],
+    bytes: Vec<u8>,
+}
+
+/// Sends the probe's burst of packets to `addr` over one socket and waits
+/// for their replies, matched to each packet by connection ID. If the first
+/// reply is a Retry, resends that Initial with the token on the same socket,
+/// since servers bind the token to the client's address and port.
+async fn probe_address(probe: &Probe, addr: SocketAddr) -> std::result::Result<AddressProbe, ProbeError> {
+    // Bind to appropriate interface based on IP version
+    let bind_addr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    let capture = match &probe.capture {
+        Some(capture) => Some((capture, probe_capture::local_addr_for(socket.local_addr()?, addr))),
+        None => None,
+    };
+
+    let packets: Vec<ProbePacket> = (0..probe.burst).map(|_| build_probe_packet(probe)).collect();
+    let mut sent_at = Vec::with_capacity(packets.len());
+    let mut rtts: Vec<Option<Duration>> = vec![None; packets.len()];
+    let mut first_reply = None;
+    let mut unrecognized = None;
 
-        // Wait for response
-        let mut buf = [0u8; 1500];
-        match timeout(Duration::from_secs(5), socket.recv_from(&mut buf)).await {
-            Ok(Ok((_len, _src))) => {
-                let latency = start.elapsed().as_millis();
-                return Ok(ServerInfo {
-                    is_online: true,
-                    latency_ms: latency,
-                });
-            },
-            Ok(Err(e)) => {
-                last_error = e.into();
-                continue;
-            },
-            Err(_) => {
-                last_error = anyhow::anyhow!("Timeout connecting to {}", addr);
-                continue;
+    let deadline = Instant::now() + probe.timeout;
+    let mut send_tick = tokio::time::interval(BURST_SPACING);
+    let mut buf = [0u8; 1500];
+
+    while rtts.iter().any(Option::is_none) {
+        tokio::select! {
+            _ = send_tick.tick(), if sent_at.len() < packets.len() => {
+                let packet = &packets[sent_at.len()].bytes;
+                socket.send_to(packet, addr).await?;
+                sent_at.push(Instant::now());
+                if let Some((capture, local)) = capture {
+                    capture.udp(local, addr, packet);
+                }
+            }
+            received = timeout_at(deadline, socket.recv_from(&mut buf)) => {
+                let Ok(received) = received else { break };
+                let (len, src) = received?;
+                if let Some((capture, local)) = capture {
+                    capture.udp(src, local, &buf[..len]);
+                }
+                let reply = match quic::parse_reply(&buf[..len]) {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        unrecognized = Some(e);
+                        continue;
+                    }
+                };
+                // Servers may send several datagrams per packet; only the first counts
+                let Some(index) = packets.iter().position(|packet| packet.scid[..] == reply.client_cid[..]) else {
+                    continue;
+                };
+                if reply.packet_type == PacketType::Retry
+                    && reply.version == QUIC_V1
+                    && !quic::is_valid_retry(&packets[index].dcid, &buf[..len])
+                {
+                    unrecognized = Some("Retry with an invalid integrity tag".to_string());
+                    continue;
+                }
+                if rtts[index].is_none() {
+                    rtts[index] = Some(sent_at[index].elapsed());
+                    first_reply.get_or_insert((index, reply));
+                }
             }
         }
     }
 
-    Err(last_error)
+    let Some((index, reply)) = first_reply else {
+        return Err(match unrecognized {
+            Some(e) => ProbeError::new(
+                ProbeOutcome::ProtocolError(e.clone()),
+                format!("Unrecognized reply from {}: {}", addr, e),
+            ),
+            None => ProbeError::new(ProbeOutcome::Timeout, format!("Timeout connecting to {}", addr)),
+        });
+    };
+    let retry = match &reply.retry_token {
+        Some(token) if probe.follow_retry => {
+            let retry_rtt = rtts[index].unwrap_or_default();
+            Some(follow_retry(probe, &socket, addr, capture, &reply, token, retry_rtt).await?)
+        }
+        _ => None,
+    };
+    let rtts: Vec<Duration> = rtts.into_iter().flatten().collect();
+    Ok(AddressProbe { stats: ProbeStats::from_rtts(sent_at.len(), &rtts), reply, retry })
+}
+
+/// Resends the Initial the server answered with `retry`, now carrying its
+/// token and addressed to the connection ID the server chose, and waits for
+/// the answer.
+async fn follow_retry(
+    probe: &Probe,
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    capture: Option<(&Capture, SocketAddr)>,
+    retry: &QuicReply,
+    token: &[u8],
+    retry_rtt: Duration,
+) -> std::result::Result<RetryInfo, ProbeError> {
+    // Packet numbers carry on after a Retry (RFC 9000 §17.2.5.3)
+    let scid = &retry.client_cid;
+    let crypto = probe.client_hello.build(scid);
+    let packet = quic::build_initial(&retry.server_cid, scid, token, 1, &crypto, probe.datagram_size);
+    socket.send_to(&packet, addr).await?;
+    let sent_at = Instant::now();
+    if let Some((capture, local)) = capture {
+        capture.udp(local, addr, &packet);
+    }
+
+    let mut info =
+        RetryInfo { retry_rtt_ms: retry_rtt.as_millis(), final_rtt_ms: None, final_reply: None, token_len: token.len() };
+    let deadline = sent_at + probe.timeout;
+    let mut buf = [0u8; 1500];
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, src) = received?;
+        if let Some((capture, local)) = capture {
+            capture.udp(src, local, &buf[..len]);
+        }
+        // Clients only accept one Retry per connection attempt (RFC 9000 §17.2.5.2)
+        let Ok(reply) = quic::parse_reply(&buf[..len]) else { continue };
+        if reply.client_cid != *scid || reply.packet_type == PacketType::Retry {
+            continue;
+        }
+        info.final_rtt_ms = Some(sent_at.elapsed().as_millis());
+        info.final_reply = Some(reply);
+        break;
+    }
+    Ok(info)
+}
+
+/// The first packet of `probe`, with fresh random connection IDs.
+fn build_probe_packet(probe: &Probe) -> ProbePacket {
+    // The client's first DCID must be at least 8 bytes
+    let mut dcid = [0u8; 8];
+    let mut scid = [0u8; 8];
+    rand::rng().fill(&mut dcid);
+    rand::rng().fill(&mut scid);
+
+    let bytes = match probe.strategy {
+        ProbeStrategy::Initial => {
+            quic::build_initial(&dcid, &scid, &[], 0, &probe.client_hello.build(&scid), probe.datagram_size)
+        }
+        ProbeStrategy::VersionNegotiation => quic::build_version_probe(&dcid, &scid),
+    };
+    ProbePacket { dcid, scid, bytes }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_cover_loss_and_jitter() {
+        let rtts = [10, 14, 12].map(Duration::from_millis);
+        let stats = ProbeStats::from_rtts(4, &rtts);
+        assert_eq!((stats.sent, stats.received), (4, 3));
+        assert_eq!(stats.loss_percent, 25.0);
+        assert_eq!(stats.min_rtt_ms, 10.0);
+        assert_eq!(stats.max_rtt_ms, 14.0);
+        assert_eq!(stats.avg_rtt_ms, 12.0);
+        assert_eq!(stats.jitter_ms, 3.0);
+    }
+
+    fn info(is_online: bool, reply: QuicReply) -> ServerInfo {
+        ServerInfo {
+            is_online,
+            latency_ms: 12,
+            stats: ProbeStats::from_rtts(1, &[Duration::from_millis(12)]),
+            reply,
+            retry: None,
+            handshake: None,
+            handshake_error: None,
+        }
+    }
+
+    fn reply(packet_type: PacketType, supported_versions: Vec<u32>) -> QuicReply {
+        QuicReply {
+            packet_type,
+            version: if packet_type == PacketType::VersionNegotiation { 0 } else { QUIC_V1 },
+            client_cid: vec![1; 8],
+            server_cid: vec![2; 8],
+            supported_versions,
+            retry_token: None,
+        }
+    }
+
+    #[test]
+    fn check_result_keeps_the_outcome() {
+        let online = CheckResult::from(&info(true, reply(PacketType::Initial, Vec::new())));
+        assert_eq!(online.status(), Some(true));
+        assert_eq!(online.outcome, ProbeOutcome::Answered);
+        assert_eq!(online.failure, None);
+
+        let mismatch = CheckResult::from(&info(false, reply(PacketType::VersionNegotiation, vec![QUIC_V2])));
+        assert_eq!(mismatch.status(), Some(false));
+        assert!(matches!(&mismatch.outcome, ProbeOutcome::ProtocolError(e) if e.starts_with("Version Negotiation")));
+        assert_eq!(mismatch.failure.as_deref(), Some("Version Negotiation (server supports v2)"));
+
+        let refused = ServerInfo {
+            handshake_error: Some("handshake failed: no application protocol".to_string()),
+            ..info(false, reply(PacketType::Initial, Vec::new()))
+        };
+        let refused = CheckResult::from(&refused);
+        assert_eq!(refused.status(), Some(false));
+        assert_eq!(refused.outcome, ProbeOutcome::ProtocolError("handshake failed: no application protocol".into()));
+    }
+
+    #[test]
+    fn stats_without_replies_are_all_loss() {
+        let stats = ProbeStats::from_rtts(3, &[]);
+        assert_eq!(stats.received, 0);
+        assert_eq!(stats.loss_percent, 100.0);
+        assert_eq!(stats.jitter_ms, 0.0);
+    }
+}
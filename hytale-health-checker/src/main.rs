@@ -1,5 +1,6 @@
 use clap::Parser;
-use hytale_health_checker::check_hytale_status;
+use hytale_health_checker::{check_hytale_addresses, check_hytale_deep, check_hytale_mtu, check_hytale_status_with_options, Capture, ProbeOptions, ProbeStrategy};
+use std::path::PathBuf;
 use std::time::Duration;
 use chrono::Local;
 
@@ -17,6 +18,42 @@ struct Args {
     /// Seconds between checks
     #[arg(short, long, default_value_t = 10)]
     interval: u64,
+
+    /// Probe every address the host resolves to (IPv4 and IPv6) and report each
+    #[arg(short, long)]
+    all_addresses: bool,
+
+    /// What to send: a full QUIC `initial`, or a cheap `version-negotiation` ping
+    #[arg(short, long, default_value = "initial")]
+    strategy: ProbeStrategy,
+
+    /// Name sent as SNI in the TLS ClientHello (defaults to --host)
+    #[arg(long)]
+    sni: Option<String>,
+
+    /// ALPN protocol to offer; repeat or comma-separate for several
+    #[arg(long, value_delimiter = ',')]
+    alpn: Vec<String>,
+
+    /// Packets sent per check; replies are matched to packets to measure loss and jitter
+    #[arg(short, long, default_value_t = 1)]
+    burst: usize,
+
+    /// Complete a full QUIC handshake and report its timing and the server certificate
+    #[arg(short, long)]
+    deep: bool,
+
+    /// Seconds to wait for replies, and for the handshake with --deep
+    #[arg(short, long, default_value_t = 5)]
+    timeout: u64,
+
+    /// Also search for the largest datagram the path carries, to spot MTU black holes
+    #[arg(short, long)]
+    mtu: bool,
+
+    /// Write every probe datagram sent and received to this pcap file
+    #[arg(long)]
+    pcap: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -26,8 +63,26 @@ async fn main() {
     println!("Starting Hytale Health Checker...");
     println!("Target: {}:{}", args.host, args.port);
     println!("Interval: {}s", args.interval);
+    let capture = args.pcap.as_ref().map(|path| {
+        Capture::create(path).unwrap_or_else(|e| {
+            eprintln!("Cannot create capture file {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+    if let Some(capture) = &capture {
+        println!("Capture: {}", capture.path().display());
+    }
     println!("-----------------------------------------------------");
 
+    let options = ProbeOptions {
+        strategy: args.strategy,
+        server_name: args.sni,
+        alpn: args.alpn,
+        burst: args.burst,
+        timeout: Some(Duration::from_secs(args.timeout)),
+        capture,
+    };
+
     // Use interval to ensure stable cadence and immediate first tick
     let mut interval = tokio::time::interval(Duration::from_secs(args.interval));
 
@@ -36,13 +91,104 @@ async fn main() {
 
         let timestamp = Local::now().format("%H:%M:%S");
 
-        match check_hytale_status(&args.host, args.port).await {
-            Ok(info) => {
+        if args.mtu {
+            match check_hytale_mtu(&args.host, args.port, &options).await {
+                Ok(report) if report.is_problem() => println!("[{}] 🟠 MTU PROBLEM | {} | {}", timestamp, report.address, report),
+                Ok(report) => println!("[{}] MTU | {} | {}", timestamp, report.address, report),
+                Err(e) => println!("[{}] MTU | Error: {}", timestamp, e),
+            }
+        }
+
+        if args.all_addresses {
+            match check_hytale_addresses(&args.host, args.port, &options).await {
+                Ok(results) => {
+                    for result in results {
+                        if let Some(reply) = &result.reply {
+                            let state = if result.is_online { "🟢 ONLINE" } else { "🔴 MISMATCH" };
+                            println!(
+                                "[{}] {} | {} {} | Ping: {}ms | {}",
+                                timestamp, state, result.family(), result.address, result.latency_ms, reply
+                            );
+                        } else {
+                            println!(
+                                "[{}] 🔴 OFFLINE | {} {} | Error: {}",
+                                timestamp, result.family(), result.address, result.error.unwrap_or_default()
+                            );
+                        }
+                    }
+                }
+                Err(e) => println!("[{}] 🔴 OFFLINE | Error: {}", timestamp, e),
+            }
+            continue;
+        }
+
+        let result = if args.deep {
+            check_hytale_deep(&args.host, args.port, &options).await
+        } else {
+            check_hytale_status_with_options(&args.host, args.port, &options).await
+        };
+
+        match result {
+            Ok(info) if info.is_online => {
                 println!(
-                    "[{}] 🟢 ONLINE | Ping: {}ms",
+                    "[{}] 🟢 ONLINE | Ping: {}ms | {} | Server CID: {}",
                     timestamp,
-                    info.latency_ms
+                    info.latency_ms,
+                    info.reply,
+                    hex(&info.reply.server_cid)
                 );
+                if info.stats.sent > 1 {
+                    println!(
+                        "           Loss: {:.0}% ({}/{}) | RTT min/avg/max: {:.1}/{:.1}/{:.1}ms | Jitter: {:.1}ms",
+                        info.stats.loss_percent,
+                        info.stats.sent - info.stats.received,
+                        info.stats.sent,
+                        info.stats.min_rtt_ms,
+                        info.stats.avg_rtt_ms,
+                        info.stats.max_rtt_ms,
+                        info.stats.jitter_ms
+                    );
+                }
+                if let Some(retry) = &info.retry {
+                    match (retry.final_rtt_ms, &retry.final_reply) {
+                        (Some(rtt), Some(reply)) => println!(
+                            "           🟠 UNDER LOAD | Retry: {}ms | Token: {} bytes | Resent Initial: {}ms | {}",
+                            retry.retry_rtt_ms, retry.token_len, rtt, reply
+                        ),
+                        _ => println!(
+                            "           🟠 UNDER LOAD | Retry: {}ms | Token: {} bytes | Resent Initial: no reply",
+                            retry.retry_rtt_ms, retry.token_len
+                        ),
+                    }
+                }
+                if let Some(handshake) = &info.handshake {
+                    println!(
+                        "           Handshake: {}ms | RTT: {}ms | ALPN: {}",
+                        handshake.handshake_ms,
+                        handshake.rtt_ms,
+                        handshake.alpn.as_deref().unwrap_or("none")
+                    );
+                    if let Some(cert) = &handshake.certificate {
+                        println!(
+                            "           Certificate: {} | Issuer: {} | Expires: {} ({} days)",
+                            cert.subject,
+                            cert.issuer,
+                            cert.not_after.format("%Y-%m-%d"),
+                            cert.days_until_expiry()
+                        );
+                    }
+                }
+            }
+            Ok(info) if info.handshake_error.is_some() => {
+                println!(
+                    "[{}] 🔴 HANDSHAKE FAILED | Ping: {}ms | {}",
+                    timestamp,
+                    info.latency_ms,
+                    info.handshake_error.unwrap_or_default()
+                );
+            }
+            Ok(info) => {
+                println!("[{}] 🔴 MISMATCH | Ping: {}ms | {}", timestamp, info.latency_ms, info.reply);
             }
             Err(e) => {
                 println!("[{}] 🔴 OFFLINE | Error: {}", timestamp, e);
@@ -50,3 +196,7 @@ async fn main() {
         }
     }
 }
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,196 @@
+//! A scriptable QUIC responder on a local UDP port, so the probe's
+//! classification and latency logic can be tested without a network or a
+//! real server. Enabled by the `mock` feature.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use hytale_health_checker::mock::{MockReply, MockResponder};
+//!
+//! // Drops every other datagram, and answers the rest like a v1 server
+//! let server = MockResponder::new(MockReply::Silence)
+//!     .then(MockReply::Initial)
+//!     .start()
+//!     .await?;
+//! let info = hytale_health_checker::check_hytale_status("127.0.0.1", server.port()).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+use crate::quic::{self, InitialHeader, InitialKeys, MIN_INITIAL_SIZE, QUIC_V1};
+
+/// What the responder answers a datagram with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockReply {
+    /// A Version Negotiation packet listing these versions.
+    VersionNegotiation(Vec<u32>),
+    /// A Retry packet carrying this token, with a valid integrity tag.
+    Retry(Vec<u8>),
+    /// A protected server Initial acknowledging the client's, the way a v1
+    /// server starts its handshake.
+    Initial,
+    /// These bytes, as they are.
+    Garbage(Vec<u8>),
+    /// No answer at all.
+    Silence,
+}
+
+/// Builder for a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockResponder {
+    script: Vec<MockReply>,
+    delay: Duration,
+    max_datagram_size: Option<usize>,
+}
+
+impl MockResponder {
+    /// A responder answering every datagram with `reply`.
+    pub fn new(reply: MockReply) -> Self {
+        Self { script: vec![reply], delay: Duration::ZERO, max_datagram_size: None }
+    }
+
+    /// Adds `reply` to the script. Datagrams are answered in script order,
+    /// starting over after the last entry.
+    pub fn then(mut self, reply: MockReply) -> Self {
+        self.script.push(reply);
+        self
+    }
+
+    /// Holds every answer back for `delay`, to simulate a round trip.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Drops datagrams larger than `size` before they reach the responder,
+    /// like a path that black-holes large UDP.
+    pub fn max_datagram_size(mut self, size: usize) -> Self {
+        self.max_datagram_size = Some(size);
+        self
+    }
+
+    /// Binds a UDP port on localhost and starts answering on it.
+    pub async fn start(self) -> io::Result<MockServer> {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await?);
+        let addr = socket.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = received.clone();
+        let task = tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            let mut answered = 0;
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let datagram = &buf[..len];
+                if self.max_datagram_size.is_some_and(|max| len > max) {
+                    continue;
+                }
+                seen.lock().unwrap().push(datagram.to_vec());
+
+                let reply = &self.script[answered % self.script.len()];
+                answered += 1;
+                let Some(bytes) = build_reply(reply, datagram) else { continue };
+
+                let socket = socket.clone();
+                let delay = self.delay;
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = socket.send_to(&bytes, peer).await;
+                });
+            }
+        });
+
+        Ok(MockServer { addr, received, task })
+    }
+}
+
+/// A running mock responder. Stops when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<Vec<u8>>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Datagrams that reached the responder so far, in arrival order.
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The bytes answering `datagram` with `reply`, or `None` for silence or a
+/// datagram without a long header to answer.
+fn build_reply(reply: &MockReply, datagram: &[u8]) -> Option<Vec<u8>> {
+    let (client_dcid, client_scid) = connection_ids(datagram)?;
+    let mut server_cid = [0u8; 8];
+    rand::rng().fill(&mut server_cid);
+
+    match reply {
+        MockReply::VersionNegotiation(versions) => {
+            let mut packet = vec![0x80 | (rand::random::<u8>() & 0x7f)];
+            packet.extend(0u32.to_be_bytes());
+            push_cid(&mut packet, client_scid);
+            push_cid(&mut packet, client_dcid);
+            for version in versions {
+                packet.extend(version.to_be_bytes());
+            }
+            Some(packet)
+        }
+        MockReply::Retry(token) => {
+            let mut packet = vec![0xf0];
+            packet.extend(QUIC_V1.to_be_bytes());
+            push_cid(&mut packet, client_scid);
+            push_cid(&mut packet, &server_cid);
+            packet.extend(token);
+            let tag = quic::retry_integrity_tag(client_dcid, &packet);
+            packet.extend(tag);
+            Some(packet)
+        }
+        MockReply::Initial => {
+            // ACK of packet 0: largest acknowledged, delay, no extra ranges, first range
+            let ack = vec![0x02, 0x00, 0x00, 0x00, 0x00];
+            let header = InitialHeader { dcid: client_scid, scid: &server_cid, token: &[], packet_number: 0 };
+            Some(quic::protect_initial(&InitialKeys::server(client_dcid), &header, ack, MIN_INITIAL_SIZE))
+        }
+        MockReply::Garbage(bytes) => Some(bytes.clone()),
+        MockReply::Silence => None,
+    }
+}
+
+/// Destination and Source Connection IDs of a long-header packet.
+fn connection_ids(datagram: &[u8]) -> Option<(&[u8], &[u8])> {
+    if datagram.first()? & 0x80 == 0 {
+        return None;
+    }
+    let dcid_len = *datagram.get(5)? as usize;
+    let dcid = datagram.get(6..6 + dcid_len)?;
+    let scid_len = *datagram.get(6 + dcid_len)? as usize;
+    let scid = datagram.get(7 + dcid_len..7 + dcid_len + scid_len)?;
+    Some((dcid, scid))
+}
+
+fn push_cid(packet: &mut Vec<u8>, cid: &[u8]) {
+    packet.push(cid.len() as u8);
+    packet.extend(cid);
+}
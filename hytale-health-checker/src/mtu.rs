@@ -0,0 +1,137 @@
+//! Path MTU black-hole detection.
+//!
+//! QUIC only works if 1200-byte datagrams get through both ways, and real
+//! clients start a little above that. Some ISPs and routers silently drop
+//! large UDP datagrams, which looks like an outage from the outside. Probing
+//! with increasing Initial sizes tells the two apart.
+
+use std::fmt;
+use std::net::SocketAddr;
+
+use crate::quic::MIN_INITIAL_SIZE;
+use crate::{probe_address, Probe, ProbeStrategy};
+
+/// Datagram sizes tried, smallest first. 1472 fills a 1500-byte Ethernet
+/// MTU over IPv4; IPv6 headers are 20 bytes longer.
+const PROBE_SIZES: [usize; 7] = [MIN_INITIAL_SIZE, 1252, 1280, 1350, 1400, 1452, 1472];
+const MAX_IPV6_SIZE: usize = 1452;
+/// Size of the first datagram of common QUIC clients. A path that can't
+/// carry it breaks real clients even if the minimal probe gets through.
+const CLIENT_DATAGRAM_SIZE: usize = 1252;
+/// Probes per size, so a single lost packet doesn't end the search.
+const ATTEMPTS_PER_SIZE: usize = 2;
+
+/// Which datagram sizes the path to a server carries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtuReport {
+    pub address: SocketAddr,
+    /// Whether a Version Negotiation ping, which the server answers with a
+    /// small datagram, got through.
+    pub answers_small_replies: bool,
+    /// Initial sizes tried, in order, and whether each was answered. The
+    /// search stops at the first size without an answer.
+    pub sizes: Vec<(usize, bool)>,
+    /// Largest Initial that was answered.
+    pub largest_answered: Option<usize>,
+}
+
+impl MtuReport {
+    /// Whether the path looks like it drops large datagrams: the server
+    /// answers small pings but not full Initials, or stops answering below
+    /// the size real clients send.
+    pub fn is_problem(&self) -> bool {
+        match self.largest_answered {
+            Some(size) => size < CLIENT_DATAGRAM_SIZE,
+            None => self.answers_small_replies,
+        }
+    }
+}
+
+impl fmt::Display for MtuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.largest_answered {
+            Some(size) => write!(f, "largest answered datagram {} bytes", size)?,
+            None if self.answers_small_replies => write!(f, "only small replies get through")?,
+            None => write!(f, "no answer")?,
+        }
+        if let Some((size, _)) = self.sizes.iter().find(|(_, answered)| !answered) {
+            write!(f, ", {} bytes lost", size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the MTU search against `addr`, with the ClientHello of `probe`.
+pub(crate) async fn diagnose(probe: &Probe, addr: SocketAddr) -> MtuReport {
+    let ping = Probe { strategy: ProbeStrategy::VersionNegotiation, burst: ATTEMPTS_PER_SIZE, ..probe.clone() };
+    let answers_small_replies = probe_address(&ping, addr).await.is_ok();
+
+    let max_size = if addr.is_ipv4() { usize::MAX } else { MAX_IPV6_SIZE };
+    let mut sizes = Vec::new();
+    for size in PROBE_SIZES.into_iter().filter(|size| *size <= max_size) {
+        let sized = Probe {
+            strategy: ProbeStrategy::Initial,
+            burst: ATTEMPTS_PER_SIZE,
+            datagram_size: size,
+            follow_retry: false,
+            ..probe.clone()
+        };
+        let answered = probe_address(&sized, addr).await.is_ok();
+        sizes.push((size, answered));
+        if !answered {
+            break;
+        }
+    }
+
+    MtuReport {
+        address: addr,
+        answers_small_replies,
+        largest_answered: sizes.iter().filter(|(_, answered)| *answered).map(|(size, _)| *size).max(),
+        sizes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(answers_small_replies: bool, sizes: &[(usize, bool)]) -> MtuReport {
+        MtuReport {
+            address: "127.0.0.1:5520".parse().unwrap(),
+            answers_small_replies,
+            sizes: sizes.to_vec(),
+            largest_answered: sizes.iter().filter(|(_, a)| *a).map(|(s, _)| *s).max(),
+        }
+    }
+
+    #[test]
+    fn full_path_is_fine() {
+        let report = report(true, &PROBE_SIZES.map(|size| (size, true)));
+        assert!(!report.is_problem());
+        assert_eq!(report.to_string(), "largest answered datagram 1472 bytes");
+    }
+
+    #[test]
+    fn pppoe_sized_path_is_fine() {
+        let report = report(true, &[(1200, true), (1252, true), (1280, true), (1350, true), (1400, true), (1452, true), (1472, false)]);
+        assert!(!report.is_problem());
+        assert_eq!(report.to_string(), "largest answered datagram 1452 bytes, 1472 bytes lost");
+    }
+
+    #[test]
+    fn dropping_client_sized_datagrams_is_a_problem() {
+        assert!(report(true, &[(1200, true), (1252, false)]).is_problem());
+    }
+
+    #[test]
+    fn only_small_replies_is_a_problem() {
+        let report = report(true, &[(1200, false)]);
+        assert!(report.is_problem());
+        assert_eq!(report.to_string(), "only small replies get through, 1200 bytes lost");
+    }
+
+    #[test]
+    fn a_silent_server_is_an_outage_not_an_mtu_problem() {
+        assert!(!report(false, &[(1200, false)]).is_problem());
+    }
+}
@@ -0,0 +1,539 @@
+//! QUIC v1 Initial packets (RFC 9000 §17.2.2), protected as described in
+//! RFC 9001 §5: keys derived from the Destination Connection ID, AES-128-GCM
+//! payload protection and AES header protection. Also reads the headers of
+//! the packets a server answers with.
+
+use std::fmt;
+
+use rand::Rng;
+use ring::aead::{self, quic, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::hkdf;
+
+/// QUIC version 1 (RFC 9000).
+pub const QUIC_V1: u32 = 0x0000_0001;
+
+/// QUIC version 2 (RFC 9369).
+pub const QUIC_V2: u32 = 0x6b33_43cf;
+
+/// Smallest UDP payload a client may carry its first Initial in (RFC 9000 §14.1).
+pub const MIN_INITIAL_SIZE: usize = 1200;
+
+/// Salt for deriving Initial secrets in QUIC v1 (RFC 9001 §5.2).
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f,
+    0x0a,
+];
+
+/// Packet number length used for the packets we send.
+const PACKET_NUMBER_LEN: usize = 4;
+
+const FRAME_PADDING: u8 = 0x00;
+const FRAME_CRYPTO: u8 = 0x06;
+
+/// Length of the integrity tag closing a Retry packet (RFC 9001 §5.8).
+const RETRY_INTEGRITY_TAG_LEN: usize = 16;
+
+/// Kind of packet a server answered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    /// The server does not speak the version we offered; lists the ones it does.
+    VersionNegotiation,
+    Initial,
+    ZeroRtt,
+    Handshake,
+    /// The server wants us to prove our address with its token first.
+    Retry,
+    /// A short-header (1-RTT) packet, which no server should send at this stage.
+    OneRtt,
+}
+
+/// The unprotected header fields of the first packet in a server's reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuicReply {
+    pub packet_type: PacketType,
+    /// Version in the long header; 0 for Version Negotiation.
+    pub version: u32,
+    /// Destination Connection ID, i.e. the Source Connection ID of the probe
+    /// this answers (empty for short headers).
+    pub client_cid: Vec<u8>,
+    /// The server's chosen Source Connection ID (empty for short headers).
+    pub server_cid: Vec<u8>,
+    /// Versions listed in a Version Negotiation packet.
+    pub supported_versions: Vec<u32>,
+    /// Token from a Retry packet, to be echoed in the next Initial.
+    pub retry_token: Option<Vec<u8>>,
+}
+
+impl QuicReply {
+    /// Whether the server rejected the version we offered.
+    pub fn is_version_mismatch(&self) -> bool {
+        self.packet_type == PacketType::VersionNegotiation
+    }
+}
+
+/// Parses the header of the first packet in `datagram`.
+///
+/// Only the header is read; payloads stay protected, as the keys for anything
+/// past the Initial need the handshake we never finish.
+pub fn parse_reply(datagram: &[u8]) -> Result<QuicReply, String> {
+    let mut reader = Reader { buf: datagram, pos: 0 };
+    let first = reader.u8()?;
+
+    if first & 0x80 == 0 {
+        return Ok(QuicReply {
+            packet_type: PacketType::OneRtt,
+            version: 0,
+            client_cid: Vec::new(),
+            server_cid: Vec::new(),
+            supported_versions: Vec::new(),
+            retry_token: None,
+        });
+    }
+
+    let version = reader.u32()?;
+    let dcid_len = reader.u8()? as usize;
+    let client_cid = reader.bytes(dcid_len)?.to_vec();
+    let scid_len = reader.u8()? as usize;
+    let server_cid = reader.bytes(scid_len)?.to_vec();
+
+    let mut reply = QuicReply {
+        packet_type: PacketType::Initial,
+        version,
+        client_cid,
+        server_cid,
+        supported_versions: Vec::new(),
+        retry_token: None,
+    };
+
+    if version == 0 {
+        reply.packet_type = PacketType::VersionNegotiation;
+        while reader.remaining() >= 4 {
+            reply.supported_versions.push(reader.u32()?);
+        }
+        return Ok(reply);
+    }
+
+    // Long packet types are numbered differently in QUIC v2 (RFC 9369 §3.2)
+    let type_bits = (first >> 4) & 0x03;
+    let type_bits = if version == QUIC_V2 { (type_bits + 3) % 4 } else { type_bits };
+    reply.packet_type = match type_bits {
+        0 => PacketType::Initial,
+        1 => PacketType::ZeroRtt,
+        2 => PacketType::Handshake,
+        _ => PacketType::Retry,
+    };
+
+    if reply.packet_type == PacketType::Retry {
+        let token_len = reader
+            .remaining()
+            .checked_sub(RETRY_INTEGRITY_TAG_LEN)
+            .ok_or("Retry packet shorter than its integrity tag")?;
+        reply.retry_token = Some(reader.bytes(token_len)?.to_vec());
+    }
+
+    Ok(reply)
+}
+
+/// Cursor over a received datagram.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("Packet truncated at byte {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Human-readable name of a QUIC version number.
+pub fn version_name(version: u32) -> String {
+    match version {
+        QUIC_V1 => "v1".to_string(),
+        QUIC_V2 => "v2".to_string(),
+        0xff00_0000..=0xff00_00ff => format!("draft-{}", version & 0xff),
+        _ if version & 0x0f0f_0f0f == 0x0a0a_0a0a => format!("{:#010x} (reserved)", version),
+        _ => format!("{:#010x}", version),
+    }
+}
+
+impl fmt::Display for PacketType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PacketType::VersionNegotiation => "Version Negotiation",
+            PacketType::Initial => "Initial",
+            PacketType::ZeroRtt => "0-RTT",
+            PacketType::Handshake => "Handshake",
+            PacketType::Retry => "Retry",
+            PacketType::OneRtt => "1-RTT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for QuicReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.packet_type {
+            PacketType::VersionNegotiation => {
+                let versions: Vec<String> = self.supported_versions.iter().map(|v| version_name(*v)).collect();
+                write!(f, "Version Negotiation (server supports {})", versions.join(", "))
+            }
+            PacketType::OneRtt => write!(f, "1-RTT"),
+            packet_type => write!(f, "{} (QUIC {})", packet_type, version_name(self.version)),
+        }
+    }
+}
+
+/// Packet protection keys for one direction of the Initial packet number space.
+pub struct InitialKeys {
+    key: [u8; 16],
+    iv: [u8; 12],
+    hp: [u8; 16],
+}
+
+impl InitialKeys {
+    /// Keys protecting the Initial packets a client sends to `dcid`.
+    pub fn client(dcid: &[u8]) -> Self {
+        Self::derive(dcid, b"client in")
+    }
+
+    /// Keys protecting the server's Initial packets on a connection the
+    /// client opened to `dcid`.
+    #[cfg(any(test, feature = "mock"))]
+    pub fn server(dcid: &[u8]) -> Self {
+        Self::derive(dcid, b"server in")
+    }
+
+    fn derive(dcid: &[u8], label: &[u8]) -> Self {
+        let initial_secret = hkdf::Salt::new(hkdf::HKDF_SHA256, &INITIAL_SALT_V1).extract(dcid);
+        let direction_secret = hkdf_expand_label(&initial_secret, label, 32);
+        let secret = hkdf::Prk::new_less_safe(hkdf::HKDF_SHA256, &direction_secret);
+
+        let mut keys = Self { key: [0; 16], iv: [0; 12], hp: [0; 16] };
+        keys.key.copy_from_slice(&hkdf_expand_label(&secret, b"quic key", 16));
+        keys.iv.copy_from_slice(&hkdf_expand_label(&secret, b"quic iv", 12));
+        keys.hp.copy_from_slice(&hkdf_expand_label(&secret, b"quic hp", 16));
+        keys
+    }
+
+    /// The AEAD nonce for `packet_number`: the IV XORed with the packet number.
+    fn nonce(&self, packet_number: u64) -> Nonce {
+        let mut nonce = self.iv;
+        for (byte, pn) in nonce[4..].iter_mut().zip(packet_number.to_be_bytes()) {
+            *byte ^= pn;
+        }
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    fn header_mask(&self, sample: &[u8]) -> [u8; 5] {
+        let hp = quic::HeaderProtectionKey::new(&quic::AES_128, &self.hp).expect("16-byte AES key");
+        hp.new_mask(sample).expect("16-byte sample")
+    }
+}
+
+/// Output length for `hkdf::Prk::expand`.
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// HKDF-Expand-Label from TLS 1.3 (RFC 8446 §7.1) with an empty context.
+fn hkdf_expand_label(secret: &hkdf::Prk, label: &[u8], len: usize) -> Vec<u8> {
+    let length = (len as u16).to_be_bytes();
+    let label_len = [(b"tls13 ".len() + label.len()) as u8];
+    let info: [&[u8]; 5] = [&length, &label_len, b"tls13 ", label, &[0]];
+
+    let mut out = vec![0u8; len];
+    secret
+        .expand(&info, Len(len))
+        .and_then(|okm| okm.fill(&mut out))
+        .expect("HKDF output length is within limits");
+    out
+}
+
+/// Appends `value` as a QUIC variable-length integer (RFC 9000 §16).
+pub fn write_varint(buf: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0x3f => buf.push(value as u8),
+        0x40..=0x3fff => buf.extend((value as u16 | 0x4000).to_be_bytes()),
+        0x4000..=0x3fff_ffff => buf.extend((value as u32 | 0x8000_0000).to_be_bytes()),
+        _ => buf.extend((value | 0xc000_0000_0000_0000).to_be_bytes()),
+    }
+}
+
+/// A reserved version of the form `0x?a?a?a?a` (RFC 9000 §15), which no
+/// server implements, so it always triggers Version Negotiation.
+pub fn greasing_version() -> u32 {
+    rand::random::<u32>() & 0xf0f0_f0f0 | 0x0a0a_0a0a
+}
+
+/// Builds a long-header packet with a greasing version, padded to
+/// [`MIN_INITIAL_SIZE`] since servers ignore smaller ones (RFC 9000 §6.1).
+/// The server must answer with Version Negotiation; no cryptography involved.
+pub fn build_version_probe(dcid: &[u8], scid: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | (rand::random::<u8>() & 0x3f)];
+    packet.extend(greasing_version().to_be_bytes());
+    packet.push(dcid.len() as u8);
+    packet.extend(dcid);
+    packet.push(scid.len() as u8);
+    packet.extend(scid);
+
+    let mut padding = vec![0u8; MIN_INITIAL_SIZE - packet.len()];
+    rand::rng().fill(&mut padding[..]);
+    packet.extend(padding);
+    packet
+}
+
+/// Builds a protected client Initial packet carrying `crypto` (a TLS
+/// ClientHello) in a CRYPTO frame, padded so the datagram is at least `size`
+/// bytes, and never less than [`MIN_INITIAL_SIZE`].
+pub fn build_initial(
+    dcid: &[u8],
+    scid: &[u8],
+    token: &[u8],
+    packet_number: u32,
+    crypto: &[u8],
+    size: usize,
+) -> Vec<u8> {
+    let mut frames = vec![FRAME_CRYPTO];
+    write_varint(&mut frames, 0); // offset
+    write_varint(&mut frames, crypto.len() as u64);
+    frames.extend(crypto);
+
+    let header = InitialHeader { dcid, scid, token, packet_number };
+    protect_initial(&InitialKeys::client(dcid), &header, frames, size.max(MIN_INITIAL_SIZE))
+}
+
+/// Fields of an Initial packet's long header.
+pub struct InitialHeader<'a> {
+    pub dcid: &'a [u8],
+    pub scid: &'a [u8],
+    pub token: &'a [u8],
+    pub packet_number: u32,
+}
+
+/// Builds an Initial packet carrying the frames in `payload`, padded so the
+/// datagram is at least `size` bytes, and protected with `keys`.
+pub fn protect_initial(keys: &InitialKeys, fields: &InitialHeader, mut payload: Vec<u8>, size: usize) -> Vec<u8> {
+    // Long header, Initial type, packet number length in the low bits
+    let mut header = vec![0xc0 | (PACKET_NUMBER_LEN as u8 - 1)];
+    header.extend(QUIC_V1.to_be_bytes());
+    header.push(fields.dcid.len() as u8);
+    header.extend(fields.dcid);
+    header.push(fields.scid.len() as u8);
+    header.extend(fields.scid);
+    write_varint(&mut header, fields.token.len() as u64);
+    header.extend(fields.token);
+
+    // The Length field (2-byte varint) covers packet number, payload and AEAD tag
+    let tag_len = aead::AES_128_GCM.tag_len();
+    let datagram_len = header.len() + 2 + PACKET_NUMBER_LEN + payload.len() + tag_len;
+    if datagram_len < size {
+        payload.resize(payload.len() + size - datagram_len, FRAME_PADDING);
+    }
+    let length = PACKET_NUMBER_LEN + payload.len() + tag_len;
+    header.extend((length as u16 | 0x4000).to_be_bytes());
+    let pn_offset = header.len();
+    header.extend(fields.packet_number.to_be_bytes());
+
+    // Payload protection, with the unprotected header as associated data
+    let key = LessSafeKey::new(UnboundKey::new(&aead::AES_128_GCM, &keys.key).expect("16-byte AES key"));
+    key.seal_in_place_append_tag(keys.nonce(fields.packet_number.into()), Aad::from(&header), &mut payload)
+        .expect("payload fits in a single AEAD seal");
+
+    // Header protection samples the ciphertext as if the packet number were 4 bytes long
+    let sample_offset = 4 - PACKET_NUMBER_LEN;
+    let mask = keys.header_mask(&payload[sample_offset..sample_offset + 16]);
+    header[0] ^= mask[0] & 0x0f;
+    for (byte, mask) in header[pn_offset..].iter_mut().zip(&mask[1..]) {
+        *byte ^= mask;
+    }
+
+    header.extend(payload);
+    header
+}
+
+/// Integrity tag closing a Retry packet (RFC 9001 §5.8): an AES-128-GCM tag
+/// over the Retry pseudo-packet, i.e. the client's original Destination
+/// Connection ID followed by the Retry packet without its tag.
+pub fn retry_integrity_tag(original_dcid: &[u8], retry: &[u8]) -> [u8; RETRY_INTEGRITY_TAG_LEN] {
+    const KEY: [u8; 16] = [
+        0xbe, 0x0c, 0x69, 0x0b, 0x9f, 0x66, 0x57, 0x5a, 0x1d, 0x76, 0x6b, 0x54, 0xe3, 0x68, 0xc8, 0x4e,
+    ];
+    const NONCE: [u8; 12] = [0x46, 0x15, 0x99, 0xd3, 0x5d, 0x63, 0x2b, 0xf2, 0x23, 0x98, 0x25, 0xbb];
+
+    let mut pseudo_packet = vec![original_dcid.len() as u8];
+    pseudo_packet.extend(original_dcid);
+    pseudo_packet.extend(retry);
+
+    let key = LessSafeKey::new(UnboundKey::new(&aead::AES_128_GCM, &KEY).expect("16-byte AES key"));
+    let tag = key
+        .seal_in_place_separate_tag(Nonce::assume_unique_for_key(NONCE), Aad::from(&pseudo_packet), &mut [])
+        .expect("empty plaintext");
+    let mut out = [0u8; RETRY_INTEGRITY_TAG_LEN];
+    out.copy_from_slice(tag.as_ref());
+    out
+}
+
+/// Whether the v1 Retry packet in `datagram` carries a valid integrity tag
+/// for the Initial sent to `original_dcid`. Anyone on the path could inject
+/// a Retry, so clients must discard those that fail the check.
+pub fn is_valid_retry(original_dcid: &[u8], datagram: &[u8]) -> bool {
+    let Some(tag_start) = datagram.len().checked_sub(RETRY_INTEGRITY_TAG_LEN) else {
+        return false;
+    };
+    retry_integrity_tag(original_dcid, &datagram[..tag_start])[..] == datagram[tag_start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from RFC 9001 Appendix A
+    const DCID: [u8; 8] = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn client_initial_keys_match_rfc_9001() {
+        let keys = InitialKeys::client(&DCID);
+        assert_eq!(keys.key.to_vec(), hex("1f369613dd76d5467730efcbe3b1a22d"));
+        assert_eq!(keys.iv.to_vec(), hex("fa044b2f42a3fd3b46fb255c"));
+        assert_eq!(keys.hp.to_vec(), hex("9f50449e04a0e810283a1e9933adedd2"));
+    }
+
+    #[test]
+    fn server_initial_keys_match_rfc_9001() {
+        let keys = InitialKeys::server(&DCID);
+        assert_eq!(keys.key.to_vec(), hex("cf3a5331653c364c88f0f379b6067e37"));
+        assert_eq!(keys.iv.to_vec(), hex("0ac1493ca1905853b0bba03e"));
+        assert_eq!(keys.hp.to_vec(), hex("c206b8d9b9f0f37644430b490eeaa314"));
+    }
+
+    #[test]
+    fn retry_integrity_tag_matches_rfc_9001() {
+        // RFC 9001 Appendix A.4
+        let retry = hex("ff000000010008f067a5502a4262b5746f6b656e");
+        assert_eq!(retry_integrity_tag(&DCID, &retry).to_vec(), hex("04a265ba2eff4d829058fb3f0f2496ba"));
+    }
+
+    #[test]
+    fn retry_with_a_bad_tag_is_rejected() {
+        let retry = hex("ff000000010008f067a5502a4262b5746f6b656e04a265ba2eff4d829058fb3f0f2496ba");
+        assert!(is_valid_retry(&DCID, &retry));
+        assert!(!is_valid_retry(&DCID[..4], &retry));
+
+        let mut tampered = retry.clone();
+        tampered[16] ^= 1;
+        assert!(!is_valid_retry(&DCID, &tampered));
+        assert!(!is_valid_retry(&DCID, &retry[..10]));
+    }
+
+    #[test]
+    fn header_mask_matches_rfc_9001() {
+        let keys = InitialKeys::client(&DCID);
+        let mask = keys.header_mask(&hex("d1b1c98dd7689fb8ec11d242b123dc9b"));
+        assert_eq!(mask.to_vec(), hex("437b9aec36"));
+    }
+
+    #[test]
+    fn varints_use_the_shortest_encoding() {
+        let encode = |value| {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            buf
+        };
+        assert_eq!(encode(37), hex("25"));
+        assert_eq!(encode(15293), hex("7bbd"));
+        assert_eq!(encode(494878333), hex("9d7f3e7d"));
+        assert_eq!(encode(151288809941952652), hex("c2197c5eff14e88c"));
+    }
+
+    #[test]
+    fn parses_version_negotiation() {
+        let datagram = hex("80000000000401020304040a0b0c0d6b3343cfff00001d");
+        let reply = parse_reply(&datagram).unwrap();
+        assert_eq!(reply.packet_type, PacketType::VersionNegotiation);
+        assert_eq!(reply.client_cid, vec![0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(reply.server_cid, vec![0x0a, 0x0b, 0x0c, 0x0d]);
+        assert_eq!(reply.supported_versions, vec![QUIC_V2, 0xff00001d]);
+        assert!(reply.is_version_mismatch());
+        assert_eq!(reply.to_string(), "Version Negotiation (server supports v2, draft-29)");
+    }
+
+    #[test]
+    fn parses_retry_token_and_new_cid() {
+        // RFC 9001 Appendix A.4
+        let datagram = hex("ff000000010008f067a5502a4262b5746f6b656e04a265ba2eff4d829058fb3f0f2496ba");
+        let reply = parse_reply(&datagram).unwrap();
+        assert_eq!(reply.packet_type, PacketType::Retry);
+        assert_eq!(reply.server_cid, hex("f067a5502a4262b5"));
+        assert_eq!(reply.retry_token, Some(b"token".to_vec()));
+    }
+
+    #[test]
+    fn parses_server_initial_header() {
+        // RFC 9001 Appendix A.3, up to the protected packet number
+        let datagram = hex("cf000000010008f067a5502a4262b5004075c0d9");
+        let reply = parse_reply(&datagram).unwrap();
+        assert_eq!(reply.packet_type, PacketType::Initial);
+        assert_eq!(reply.version, QUIC_V1);
+        assert_eq!(reply.server_cid, hex("f067a5502a4262b5"));
+    }
+
+    #[test]
+    fn truncated_reply_is_an_error() {
+        assert!(parse_reply(&hex("c0000000010811")).is_err());
+    }
+
+    #[test]
+    fn version_probe_uses_a_greasing_version() {
+        let packet = build_version_probe(&DCID, &[1, 2, 3, 4]);
+        assert_eq!(packet.len(), MIN_INITIAL_SIZE);
+        assert_eq!(packet[0] & 0xc0, 0xc0);
+        let version = u32::from_be_bytes([packet[1], packet[2], packet[3], packet[4]]);
+        assert_eq!(version & 0x0f0f_0f0f, 0x0a0a_0a0a);
+        assert_eq!(&packet[5..14], &[8, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08]);
+    }
+
+    #[test]
+    fn initial_fills_the_minimum_datagram() {
+        let packet = build_initial(&DCID, &[1, 2, 3, 4], &[], 0, &[0x01; 300], MIN_INITIAL_SIZE);
+        assert_eq!(packet.len(), MIN_INITIAL_SIZE);
+        // Header protection leaves the form and fixed bits alone
+        assert_eq!(packet[0] & 0xf0, 0xc0);
+        assert_eq!(&packet[1..5], &QUIC_V1.to_be_bytes());
+    }
+
+    #[test]
+    fn initial_fills_the_requested_size() {
+        let packet = build_initial(&DCID, &[1, 2, 3, 4], &[], 0, &[0x01; 300], 1472);
+        assert_eq!(packet.len(), 1472);
+        // Never below the minimum QUIC allows
+        let packet = build_initial(&DCID, &[1, 2, 3, 4], &[], 0, &[0x01; 300], 600);
+        assert_eq!(packet.len(), MIN_INITIAL_SIZE);
+    }
+}
@@ -0,0 +1,297 @@
+//! TLS 1.3 ClientHello (RFC 8446 §4.1.2) as carried in a QUIC Initial (RFC 9001 §4).
+//!
+//! Servers differ in what they insist on (SNI, a specific ALPN protocol), so
+//! those parts are configurable through [`ClientHelloBuilder`].
+//!
+//! The probe never completes the handshake, so the key share's private half
+//! is thrown away; the hello only has to be good enough for the server to answer.
+
+use std::net::IpAddr;
+
+use rand::Rng;
+use ring::agreement::{self, EphemeralPrivateKey, X25519};
+use ring::rand::SystemRandom;
+
+use crate::quic::write_varint;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+
+const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+const TLS_AES_256_GCM_SHA384: u16 = 0x1302;
+const TLS_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+
+const EXT_SERVER_NAME: u16 = 0x0000;
+const EXT_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXT_ALPN: u16 = 0x0010;
+const EXT_SUPPORTED_VERSIONS: u16 = 0x002b;
+const EXT_KEY_SHARE: u16 = 0x0033;
+const EXT_QUIC_TRANSPORT_PARAMETERS: u16 = 0x0039;
+
+const GROUP_X25519: u16 = 0x001d;
+const TLS13: u16 = 0x0304;
+
+const SIGNATURE_ALGORITHMS: [u16; 6] = [
+    0x0403, // ecdsa_secp256r1_sha256
+    0x0804, // rsa_pss_rsae_sha256
+    0x0401, // rsa_pkcs1_sha256
+    0x0503, // ecdsa_secp384r1_sha384
+    0x0805, // rsa_pss_rsae_sha384
+    0x0807, // ed25519
+];
+
+// Transport parameter IDs (RFC 9000 §18.2)
+const TP_MAX_IDLE_TIMEOUT: u64 = 0x01;
+const TP_MAX_UDP_PAYLOAD_SIZE: u64 = 0x03;
+const TP_INITIAL_MAX_DATA: u64 = 0x04;
+const TP_INITIAL_MAX_STREAM_DATA_BIDI_LOCAL: u64 = 0x05;
+const TP_INITIAL_MAX_STREAM_DATA_BIDI_REMOTE: u64 = 0x06;
+const TP_INITIAL_MAX_STREAM_DATA_UNI: u64 = 0x07;
+const TP_INITIAL_MAX_STREAMS_BIDI: u64 = 0x08;
+const TP_INITIAL_MAX_STREAMS_UNI: u64 = 0x09;
+const TP_INITIAL_SOURCE_CONNECTION_ID: u64 = 0x0f;
+
+/// QUIC transport parameters (RFC 9000 §18.2) advertised in the ClientHello.
+///
+/// The defaults are those of an ordinary client, so servers don't reject the hello.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportParameters {
+    pub max_idle_timeout_ms: u64,
+    pub max_udp_payload_size: u64,
+    pub initial_max_data: u64,
+    pub initial_max_stream_data_bidi_local: u64,
+    pub initial_max_stream_data_bidi_remote: u64,
+    pub initial_max_stream_data_uni: u64,
+    pub initial_max_streams_bidi: u64,
+    pub initial_max_streams_uni: u64,
+}
+
+impl Default for TransportParameters {
+    fn default() -> Self {
+        Self {
+            max_idle_timeout_ms: 30_000,
+            max_udp_payload_size: 1472,
+            initial_max_data: 1 << 20,
+            initial_max_stream_data_bidi_local: 1 << 18,
+            initial_max_stream_data_bidi_remote: 1 << 18,
+            initial_max_stream_data_uni: 1 << 18,
+            initial_max_streams_bidi: 16,
+            initial_max_streams_uni: 16,
+        }
+    }
+}
+
+/// Builds the ClientHello a QUIC client sends in its first Initial.
+///
+/// `supported_versions` (TLS 1.3 only), `supported_groups`,
+/// `signature_algorithms` and an X25519 `key_share` are always included, as
+/// TLS 1.3 requires them. SNI, ALPN and the transport parameters are
+/// configurable.
+#[derive(Debug, Clone, Default)]
+pub struct ClientHelloBuilder {
+    server_name: Option<String>,
+    alpn: Vec<String>,
+    transport_parameters: TransportParameters,
+}
+
+impl ClientHelloBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name sent as SNI. IP addresses are not allowed in SNI (RFC 6066 §3) and are left out.
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Application protocols offered via ALPN, most preferred first.
+    pub fn alpn<I, S>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.alpn = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn transport_parameters(mut self, parameters: TransportParameters) -> Self {
+        self.transport_parameters = parameters;
+        self
+    }
+
+    /// Builds the ClientHello handshake message for a QUIC connection whose
+    /// client Source Connection ID is `scid`.
+    pub fn build(&self, scid: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend(0x0303u16.to_be_bytes()); // legacy_version: TLS 1.2
+
+        let mut random = [0u8; 32];
+        rand::rng().fill(&mut random);
+        body.extend(random);
+
+        body.push(0); // legacy_session_id: QUIC forbids middlebox compatibility mode
+
+        let suites = [TLS_AES_128_GCM_SHA256, TLS_AES_256_GCM_SHA384, TLS_CHACHA20_POLY1305_SHA256];
+        body.extend(((suites.len() * 2) as u16).to_be_bytes());
+        suites.iter().for_each(|suite| body.extend(suite.to_be_bytes()));
+
+        body.extend([1, 0]); // legacy_compression_methods: null only
+
+        let extensions = self.extensions(scid);
+        body.extend((extensions.len() as u16).to_be_bytes());
+        body.extend(extensions);
+
+        let mut message = vec![HANDSHAKE_CLIENT_HELLO];
+        message.extend(&(body.len() as u32).to_be_bytes()[1..]); // u24 length
+        message.extend(body);
+        message
+    }
+
+    fn extensions(&self, scid: &[u8]) -> Vec<u8> {
+        let mut extensions = Vec::new();
+
+        if let Some(name) = self.server_name.as_deref()
+            && !name.is_empty()
+            && name.parse::<IpAddr>().is_err()
+        {
+            let name = name.as_bytes();
+            let mut data = Vec::new();
+            data.extend(((name.len() + 3) as u16).to_be_bytes()); // server_name_list length
+            data.push(0); // name_type: host_name
+            data.extend((name.len() as u16).to_be_bytes());
+            data.extend(name);
+            push_extension(&mut extensions, EXT_SERVER_NAME, &data);
+        }
+
+        if !self.alpn.is_empty() {
+            let mut list = Vec::new();
+            for protocol in &self.alpn {
+                list.push(protocol.len() as u8);
+                list.extend(protocol.as_bytes());
+            }
+            let mut data = (list.len() as u16).to_be_bytes().to_vec();
+            data.extend(list);
+            push_extension(&mut extensions, EXT_ALPN, &data);
+        }
+
+        push_extension(&mut extensions, EXT_SUPPORTED_VERSIONS, &[2, (TLS13 >> 8) as u8, TLS13 as u8]);
+
+        let mut groups = 2u16.to_be_bytes().to_vec();
+        groups.extend(GROUP_X25519.to_be_bytes());
+        push_extension(&mut extensions, EXT_SUPPORTED_GROUPS, &groups);
+
+        let mut algorithms = ((SIGNATURE_ALGORITHMS.len() * 2) as u16).to_be_bytes().to_vec();
+        SIGNATURE_ALGORITHMS.iter().for_each(|alg| algorithms.extend(alg.to_be_bytes()));
+        push_extension(&mut extensions, EXT_SIGNATURE_ALGORITHMS, &algorithms);
+
+        let public_key = x25519_public_key();
+        let mut key_share = ((public_key.len() + 4) as u16).to_be_bytes().to_vec(); // client_shares length
+        key_share.extend(GROUP_X25519.to_be_bytes());
+        key_share.extend((public_key.len() as u16).to_be_bytes());
+        key_share.extend(public_key);
+        push_extension(&mut extensions, EXT_KEY_SHARE, &key_share);
+
+        let parameters = self.transport_parameters.encode(scid);
+        push_extension(&mut extensions, EXT_QUIC_TRANSPORT_PARAMETERS, &parameters);
+
+        extensions
+    }
+}
+
+fn push_extension(extensions: &mut Vec<u8>, extension_type: u16, data: &[u8]) {
+    extensions.extend(extension_type.to_be_bytes());
+    extensions.extend((data.len() as u16).to_be_bytes());
+    extensions.extend(data);
+}
+
+/// A fresh X25519 public key for the key_share extension.
+fn x25519_public_key() -> Vec<u8> {
+    let private_key = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+        .expect("system randomness is available");
+    let public_key: agreement::PublicKey = private_key.compute_public_key().expect("X25519 public key");
+    public_key.as_ref().to_vec()
+}
+
+impl TransportParameters {
+    /// Encodes the parameters, adding `initial_source_connection_id` (mandatory, RFC 9000 §7.3).
+    fn encode(&self, scid: &[u8]) -> Vec<u8> {
+        let mut params = Vec::new();
+        let integers = [
+            (TP_MAX_IDLE_TIMEOUT, self.max_idle_timeout_ms),
+            (TP_MAX_UDP_PAYLOAD_SIZE, self.max_udp_payload_size),
+            (TP_INITIAL_MAX_DATA, self.initial_max_data),
+            (TP_INITIAL_MAX_STREAM_DATA_BIDI_LOCAL, self.initial_max_stream_data_bidi_local),
+            (TP_INITIAL_MAX_STREAM_DATA_BIDI_REMOTE, self.initial_max_stream_data_bidi_remote),
+            (TP_INITIAL_MAX_STREAM_DATA_UNI, self.initial_max_stream_data_uni),
+            (TP_INITIAL_MAX_STREAMS_BIDI, self.initial_max_streams_bidi),
+            (TP_INITIAL_MAX_STREAMS_UNI, self.initial_max_streams_uni),
+        ];
+        for (id, value) in integers {
+            let mut encoded = Vec::new();
+            write_varint(&mut encoded, value);
+            write_varint(&mut params, id);
+            write_varint(&mut params, encoded.len() as u64);
+            params.extend(encoded);
+        }
+
+        write_varint(&mut params, TP_INITIAL_SOURCE_CONNECTION_ID);
+        write_varint(&mut params, scid.len() as u64);
+        params.extend(scid);
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Extension types in the order they appear in `hello`.
+    fn extension_types(hello: &[u8]) -> Vec<u16> {
+        // type (1) + length (3) + version (2) + random (32) + session id (1)
+        let mut pos = 4 + 2 + 32 + 1;
+        let suites_len = u16::from_be_bytes([hello[pos], hello[pos + 1]]) as usize;
+        pos += 2 + suites_len + 2; // suites, then the compression methods
+        pos += 2; // extensions length
+
+        let mut types = Vec::new();
+        while pos < hello.len() {
+            types.push(u16::from_be_bytes([hello[pos], hello[pos + 1]]));
+            let len = u16::from_be_bytes([hello[pos + 2], hello[pos + 3]]) as usize;
+            pos += 4 + len;
+        }
+        types
+    }
+
+    #[test]
+    fn length_fields_cover_the_message() {
+        let hello = ClientHelloBuilder::new().server_name("play.example.com").alpn(["h3"]).build(&[7; 8]);
+        assert_eq!(hello[0], HANDSHAKE_CLIENT_HELLO);
+        let len = u32::from_be_bytes([0, hello[1], hello[2], hello[3]]) as usize;
+        assert_eq!(len, hello.len() - 4);
+    }
+
+    #[test]
+    fn sni_and_alpn_are_optional() {
+        let minimal = extension_types(&ClientHelloBuilder::new().build(&[7; 8]));
+        assert_eq!(
+            minimal,
+            vec![
+                EXT_SUPPORTED_VERSIONS,
+                EXT_SUPPORTED_GROUPS,
+                EXT_SIGNATURE_ALGORITHMS,
+                EXT_KEY_SHARE,
+                EXT_QUIC_TRANSPORT_PARAMETERS,
+            ]
+        );
+
+        let full = extension_types(&ClientHelloBuilder::new().server_name("play.example.com").alpn(["h3"]).build(&[7; 8]));
+        assert_eq!(&full[..2], &[EXT_SERVER_NAME, EXT_ALPN]);
+    }
+
+    #[test]
+    fn ip_addresses_are_not_sent_as_sni() {
+        let types = extension_types(&ClientHelloBuilder::new().server_name("192.168.1.50").build(&[7; 8]));
+        assert!(!types.contains(&EXT_SERVER_NAME));
+    }
+}
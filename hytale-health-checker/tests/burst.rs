@@ -0,0 +1,48 @@
+use hytale_health_checker::mock::{MockReply, MockResponder};
+use hytale_health_checker::{
+    check_hytale_status_with_options, probe_outcome, ProbeOptions, ProbeOutcome, ProbeStrategy, QUIC_V1,
+};
+
+fn burst_options(burst: usize) -> ProbeOptions {
+    ProbeOptions { strategy: ProbeStrategy::VersionNegotiation, burst, ..Default::default() }
+}
+
+fn version_negotiation() -> MockReply {
+    MockReply::VersionNegotiation(vec![QUIC_V1])
+}
+
+#[tokio::test]
+async fn burst_survives_and_measures_packet_loss() {
+    let server = MockResponder::new(MockReply::Silence).then(version_negotiation()).start().await.unwrap();
+
+    let info = check_hytale_status_with_options("127.0.0.1", server.port(), &burst_options(4)).await.unwrap();
+
+    assert!(info.is_online);
+    assert_eq!(info.stats.sent, 4);
+    assert_eq!(info.stats.received, 2);
+    assert_eq!(info.stats.loss_percent, 50.0);
+    assert!(info.stats.min_rtt_ms <= info.stats.avg_rtt_ms);
+    assert!(info.stats.avg_rtt_ms <= info.stats.max_rtt_ms);
+}
+
+#[tokio::test]
+async fn burst_without_loss_answers_every_packet() {
+    let server = MockResponder::new(version_negotiation()).start().await.unwrap();
+
+    let info = check_hytale_status_with_options("127.0.0.1", server.port(), &burst_options(5)).await.unwrap();
+
+    assert!(info.is_online);
+    assert_eq!((info.stats.sent, info.stats.received), (5, 5));
+    assert_eq!(info.stats.loss_percent, 0.0);
+    assert_eq!(server.received().len(), 5);
+}
+
+#[tokio::test]
+async fn burst_with_every_packet_lost_times_out() {
+    let server = MockResponder::new(MockReply::Silence).start().await.unwrap();
+
+    let result = check_hytale_status_with_options("127.0.0.1", server.port(), &burst_options(3)).await;
+
+    let error = result.err().expect("no reply should be an error");
+    assert_eq!(probe_outcome(&error), ProbeOutcome::Timeout, "unexpected error: {}", error);
+}
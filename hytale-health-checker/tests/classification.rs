@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use hytale_health_checker::mock::{MockReply, MockResponder};
+use hytale_health_checker::{
+    check_hytale_addresses, check_hytale_status, check_hytale_status_with_options, probe_outcome, HealthCheck,
+    HytaleCheck, PacketType, ProbeOptions, ProbeOutcome, ProbeStrategy, QUIC_V1, QUIC_V2,
+};
+
+fn version_negotiation_options() -> ProbeOptions {
+    ProbeOptions { strategy: ProbeStrategy::VersionNegotiation, ..Default::default() }
+}
+
+#[tokio::test]
+async fn server_initial_is_online() {
+    let server = MockResponder::new(MockReply::Initial).start().await.unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(info.is_online);
+    assert_eq!(info.reply.packet_type, PacketType::Initial);
+    assert_eq!(info.reply.version, QUIC_V1);
+    assert_eq!(info.reply.server_cid.len(), 8);
+}
+
+#[tokio::test]
+async fn retry_is_online_and_carries_the_token() {
+    let server = MockResponder::new(MockReply::Retry(b"address-token".to_vec())).start().await.unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(info.is_online);
+    assert_eq!(info.reply.packet_type, PacketType::Retry);
+    assert_eq!(info.reply.retry_token, Some(b"address-token".to_vec()));
+}
+
+#[tokio::test]
+async fn version_negotiation_to_an_initial_is_a_mismatch() {
+    let server = MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V2])).start().await.unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(!info.is_online);
+    assert!(info.reply.is_version_mismatch());
+    assert_eq!(info.reply.supported_versions, vec![QUIC_V2]);
+}
+
+#[tokio::test]
+async fn version_negotiation_ping_needs_v1_in_the_list() {
+    let with_v1 = MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V2, QUIC_V1])).start().await.unwrap();
+    let without_v1 = MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V2])).start().await.unwrap();
+
+    let online = check_hytale_status_with_options("127.0.0.1", with_v1.port(), &version_negotiation_options())
+        .await
+        .unwrap();
+    let mismatch = check_hytale_status_with_options("127.0.0.1", without_v1.port(), &version_negotiation_options())
+        .await
+        .unwrap();
+
+    assert!(online.is_online);
+    assert!(!mismatch.is_online);
+}
+
+#[tokio::test]
+async fn garbage_is_an_error() {
+    let server = MockResponder::new(MockReply::Garbage(vec![0xc0, 0x00])).start().await.unwrap();
+
+    let error = check_hytale_status("127.0.0.1", server.port()).await.err().expect("garbage should fail");
+
+    assert!(matches!(probe_outcome(&error), ProbeOutcome::ProtocolError(_)), "unexpected error: {}", error);
+    assert!(error.to_string().starts_with("Unrecognized reply"));
+}
+
+#[tokio::test]
+async fn silence_times_out() {
+    let server = MockResponder::new(MockReply::Silence).start().await.unwrap();
+
+    let error = check_hytale_status("127.0.0.1", server.port()).await.err().expect("silence should fail");
+
+    assert_eq!(probe_outcome(&error), ProbeOutcome::Timeout, "unexpected error: {}", error);
+    assert_eq!(server.received().len(), 1);
+}
+
+#[tokio::test]
+async fn timeout_is_configurable() {
+    let server = MockResponder::new(MockReply::Initial).delay(Duration::from_millis(300)).start().await.unwrap();
+    let options = |timeout_ms| ProbeOptions { timeout: Some(Duration::from_millis(timeout_ms)), ..Default::default() };
+
+    let error = check_hytale_status_with_options("127.0.0.1", server.port(), &options(100)).await.err();
+    let info = check_hytale_status_with_options("127.0.0.1", server.port(), &options(2000)).await.unwrap();
+
+    assert_eq!(probe_outcome(&error.expect("a late reply should time out")), ProbeOutcome::Timeout);
+    assert!(info.is_online);
+}
+
+#[tokio::test]
+async fn latency_covers_the_server_delay() {
+    let server = MockResponder::new(MockReply::Initial).delay(Duration::from_millis(150)).start().await.unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(info.latency_ms >= 150, "latency {}ms", info.latency_ms);
+    assert!(info.latency_ms < 1000, "latency {}ms", info.latency_ms);
+}
+
+#[tokio::test]
+async fn probe_is_a_padded_v1_initial() {
+    let server = MockResponder::new(MockReply::Initial).start().await.unwrap();
+
+    check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    let received = server.received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].len(), 1200);
+    assert_eq!(received[0][0] & 0xf0, 0xc0);
+    assert_eq!(&received[0][1..5], &QUIC_V1.to_be_bytes());
+}
+
+#[tokio::test]
+async fn address_results_classify_the_reply() {
+    let server = MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V2])).start().await.unwrap();
+
+    let results = check_hytale_addresses("127.0.0.1", server.port(), &ProbeOptions::default()).await.unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].is_online);
+    assert_eq!(results[0].error, None);
+    assert!(results[0].reply.as_ref().is_some_and(|reply| reply.is_version_mismatch()));
+}
+
+#[tokio::test]
+async fn health_check_reports_through_the_common_result() {
+    let online = MockResponder::new(MockReply::Initial).start().await.unwrap();
+    let mismatch = MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V2])).start().await.unwrap();
+    let checks: Vec<Box<dyn HealthCheck>> = vec![
+        Box::new(HytaleCheck::new("127.0.0.1", online.port(), ProbeOptions::default())),
+        Box::new(HytaleCheck::new("127.0.0.1", mismatch.port(), ProbeOptions::default())),
+        Box::new(HytaleCheck::new("no-such-host.invalid", 5520, ProbeOptions::default())),
+    ];
+
+    let mut results = Vec::new();
+    for check in &checks {
+        assert_eq!(check.name(), "Hytale");
+        results.push(check.check().await);
+    }
+
+    assert_eq!(results[0].status(), Some(true));
+    assert_eq!(results[0].failure, None);
+    assert_eq!(results[1].status(), Some(false));
+    assert!(matches!(results[1].outcome, ProbeOutcome::ProtocolError(_)));
+    assert!(results[1].failure.as_ref().is_some_and(|failure| failure.starts_with("Version Negotiation")));
+    assert!(matches!(results[2].outcome, ProbeOutcome::DnsFailure(_)));
+    assert_eq!(results[2].status(), None);
+}
+
+#[tokio::test]
+async fn retry_is_followed_with_the_token() {
+    let server = MockResponder::new(MockReply::Retry(b"address-token".to_vec()))
+        .then(MockReply::Initial)
+        .start()
+        .await
+        .unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(info.is_online);
+    let retry = info.retry.expect("the Retry should be followed");
+    assert_eq!(retry.token_len, 13);
+    assert!(retry.final_rtt_ms.is_some());
+    assert_eq!(retry.final_reply.map(|reply| reply.packet_type), Some(PacketType::Initial));
+    // The resent Initial goes to the server's new connection ID with the token
+    let received = server.received();
+    assert_eq!(received.len(), 2);
+    assert_eq!(&received[1][6..14], &info.reply.server_cid[..]);
+    assert!(received[1].windows(13).any(|window| window == b"address-token"));
+}
+
+#[tokio::test]
+async fn unanswered_retry_is_still_online() {
+    let server = MockResponder::new(MockReply::Retry(b"address-token".to_vec()))
+        .then(MockReply::Silence)
+        .start()
+        .await
+        .unwrap();
+
+    let info = check_hytale_status("127.0.0.1", server.port()).await.unwrap();
+
+    assert!(info.is_online);
+    let retry = info.retry.unwrap();
+    assert_eq!((retry.final_rtt_ms, retry.final_reply), (None, None));
+}
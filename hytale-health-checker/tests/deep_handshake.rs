@@ -0,0 +1,99 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use chrono::{Datelike, Utc};
+use hytale_health_checker::{check_hytale_deep, check_hytale_handshake, ProbeOptions};
+use rcgen::{CertificateParams, DnType, KeyPair};
+use rustls::pki_types::PrivateKeyDer;
+
+/// Starts a QUIC server on localhost with a self-signed certificate for
+/// `CN=hytale.test`, expiring at the start of 2031, that requires `alpn`.
+fn start_server(alpn: &str) -> SocketAddr {
+    let mut params = CertificateParams::new(vec!["hytale.test".to_string()]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, "hytale.test");
+    params.not_after = rcgen::date_time_ymd(2031, 1, 1);
+    let key_pair = KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key_pair).unwrap();
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut tls = rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.der().clone()], PrivateKeyDer::Pkcs8(key_pair.serialize_der().into()))
+        .unwrap();
+    tls.alpn_protocols = vec![alpn.as_bytes().to_vec()];
+
+    let crypto = quinn::crypto::rustls::QuicServerConfig::try_from(tls).unwrap();
+    let config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let endpoint = quinn::Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = endpoint.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            tokio::spawn(async move {
+                if let Ok(connection) = incoming.await {
+                    connection.closed().await;
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn deep_check_reports_handshake_and_certificate() {
+    let addr = start_server("hytale");
+    let options = ProbeOptions { alpn: vec!["hytale".to_string()], ..Default::default() };
+
+    let info = check_hytale_deep("127.0.0.1", addr.port(), &options).await.unwrap();
+
+    assert!(info.is_online);
+    assert_eq!(info.handshake_error, None);
+    let handshake = info.handshake.expect("handshake info");
+    assert_eq!(handshake.alpn.as_deref(), Some("hytale"));
+    assert!(handshake.handshake_ms < 5000);
+
+    let cert = handshake.certificate.expect("server certificate");
+    assert_eq!(cert.subject, "CN=hytale.test");
+    assert_eq!(cert.issuer, "CN=hytale.test");
+    assert_eq!(cert.not_after.year(), 2031);
+    assert!(cert.not_before <= Utc::now());
+    assert!(cert.days_until_expiry() > 0);
+}
+
+#[tokio::test]
+async fn deep_check_reports_a_refused_handshake() {
+    let addr = start_server("hytale");
+    let options = ProbeOptions { alpn: vec!["something-else".to_string()], ..Default::default() };
+
+    let info = check_hytale_deep("127.0.0.1", addr.port(), &options).await.unwrap();
+
+    // The server still answers the probe, but won't finish the handshake
+    assert!(!info.is_online);
+    assert!(info.handshake.is_none());
+    let error = info.handshake_error.expect("handshake error");
+    assert!(error.contains("failed"), "unexpected error: {}", error);
+}
+
+#[tokio::test]
+async fn handshake_goes_to_the_given_address() {
+    let addr = start_server("hytale");
+    let options = ProbeOptions { alpn: vec!["hytale".to_string()], ..Default::default() };
+
+    let handshake = check_hytale_handshake("hytale.test", addr, &options).await.unwrap();
+
+    assert_eq!(handshake.alpn.as_deref(), Some("hytale"));
+    assert_eq!(handshake.certificate.expect("server certificate").subject, "CN=hytale.test");
+}
+
+#[tokio::test]
+async fn deep_check_fails_without_a_server() {
+    // Bind and drop a socket to find a port nothing listens on
+    let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+    let result = check_hytale_deep("127.0.0.1", port, &ProbeOptions::default()).await;
+
+    assert!(result.is_err());
+}
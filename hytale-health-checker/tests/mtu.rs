@@ -0,0 +1,40 @@
+use hytale_health_checker::mock::{MockReply, MockResponder};
+use hytale_health_checker::{check_hytale_mtu, ProbeOptions, QUIC_V1};
+
+/// A server behind a path that drops datagrams larger than `max_size`.
+/// Everything that gets through is answered with a small Version Negotiation.
+fn behind_mtu(max_size: usize) -> MockResponder {
+    MockResponder::new(MockReply::VersionNegotiation(vec![QUIC_V1])).max_datagram_size(max_size)
+}
+
+#[tokio::test]
+async fn finds_the_largest_datagram_through_the_path() {
+    let server = behind_mtu(1300).start().await.unwrap();
+
+    let report = check_hytale_mtu("127.0.0.1", server.port(), &ProbeOptions::default()).await.unwrap();
+
+    assert!(report.answers_small_replies);
+    assert_eq!(report.largest_answered, Some(1280));
+    assert_eq!(report.sizes, vec![(1200, true), (1252, true), (1280, true), (1350, false)]);
+    assert!(!report.is_problem());
+}
+
+#[tokio::test]
+async fn flags_a_path_that_only_carries_minimal_datagrams() {
+    let server = behind_mtu(1200).start().await.unwrap();
+
+    let report = check_hytale_mtu("127.0.0.1", server.port(), &ProbeOptions::default()).await.unwrap();
+
+    assert_eq!(report.largest_answered, Some(1200));
+    assert!(report.is_problem());
+}
+
+#[tokio::test]
+async fn full_path_answers_every_size() {
+    let server = MockResponder::new(MockReply::Initial).start().await.unwrap();
+
+    let report = check_hytale_mtu("127.0.0.1", server.port(), &ProbeOptions::default()).await.unwrap();
+
+    assert_eq!(report.largest_answered, Some(1472));
+    assert!(report.sizes.iter().all(|(_, answered)| *answered));
+}
@@ -0,0 +1,340 @@
+//! Writes the traffic of health-check probes to a pcap file, so failed probes
+//! can be opened in Wireshark.
+//!
+//! The checkers only see socket payloads, so IP, UDP and TCP headers are
+//! synthesized from the socket addresses. A TCP connection is recorded as a
+//! plausible flow (handshake, data segments, FIN) with sequence numbers that
+//! follow the bytes actually exchanged.
+
+mod packet;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use packet::{TCP_ACK, TCP_FIN, TCP_PSH, TCP_RST, TCP_SYN};
+
+/// pcap magic for microsecond timestamps.
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+/// Largest packet recorded in full.
+const SNAPLEN: u32 = 65535;
+/// Raw IP packets with no link-layer header; Wireshark tells v4 and v6 apart.
+const LINKTYPE_RAW: u32 = 101;
+
+/// A pcap file probes write their traffic to. Cheap to clone; clones share
+/// the file.
+///
+/// Writing is best effort: a full disk must not fail a health check, so write
+/// errors are dropped.
+#[derive(Clone)]
+pub struct Capture {
+    file: Arc<Mutex<File>>,
+    path: Arc<PathBuf>,
+}
+
+impl Capture {
+    /// Creates (or truncates) the pcap file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::create(path.as_ref())?;
+
+        let mut header = Vec::with_capacity(24);
+        header.extend(PCAP_MAGIC.to_le_bytes());
+        header.extend(2u16.to_le_bytes()); // version 2.4
+        header.extend(4u16.to_le_bytes());
+        header.extend(0i32.to_le_bytes()); // timezone offset
+        header.extend(0u32.to_le_bytes()); // timestamp accuracy
+        header.extend(SNAPLEN.to_le_bytes());
+        header.extend(LINKTYPE_RAW.to_le_bytes());
+        file.write_all(&header)?;
+
+        Ok(Self { file: Arc::new(Mutex::new(file)), path: Arc::new(path.as_ref().to_path_buf()) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records a UDP datagram from `src` to `dst`.
+    pub fn udp(&self, src: SocketAddr, dst: SocketAddr, payload: &[u8]) {
+        self.write_packet(&packet::udp(src, dst, payload));
+    }
+
+    /// Starts recording a TCP connection from `local` to `remote`.
+    pub fn tcp_flow(&self, local: SocketAddr, remote: SocketAddr) -> TcpFlow {
+        // Initial sequence numbers only need to look plausible
+        let isn = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+        TcpFlow {
+            capture: self.clone(),
+            local,
+            remote,
+            local_seq: isn,
+            remote_seq: isn.rotate_left(16),
+            closed: false,
+        }
+    }
+
+    fn write_packet(&self, packet: &[u8]) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let captured = packet.len().min(SNAPLEN as usize);
+
+        let mut record = Vec::with_capacity(16 + captured);
+        record.extend((now.as_secs() as u32).to_le_bytes());
+        record.extend(now.subsec_micros().to_le_bytes());
+        record.extend((captured as u32).to_le_bytes());
+        record.extend((packet.len() as u32).to_le_bytes());
+        record.extend(&packet[..captured]);
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(&record);
+        }
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Capture").field(&self.path).finish()
+    }
+}
+
+/// Address a socket bound to `local` uses to reach `remote`.
+///
+/// Sockets bound to the wildcard address report `0.0.0.0` or `::`, which
+/// makes for a confusing capture. This asks the OS which source address it
+/// would route through, without sending anything.
+pub fn local_addr_for(local: SocketAddr, remote: SocketAddr) -> SocketAddr {
+    if !local.ip().is_unspecified() {
+        return local;
+    }
+    let unspecified = match remote.ip() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let routed = UdpSocket::bind((unspecified, 0))
+        .and_then(|socket| socket.connect(remote).map(|_| socket))
+        .and_then(|socket| socket.local_addr());
+    match routed {
+        Ok(routed) => SocketAddr::new(routed.ip(), local.port()),
+        Err(_) => local,
+    }
+}
+
+/// A TCP connection being recorded, tracking sequence numbers on both sides.
+pub struct TcpFlow {
+    capture: Capture,
+    local: SocketAddr,
+    remote: SocketAddr,
+    local_seq: u32,
+    remote_seq: u32,
+    closed: bool,
+}
+
+impl TcpFlow {
+    /// Records the three-way handshake of a connection that was accepted.
+    pub fn established(&mut self) {
+        self.outgoing(TCP_SYN, &[]);
+        self.local_seq = self.local_seq.wrapping_add(1);
+        self.incoming(TCP_SYN | TCP_ACK, &[]);
+        self.remote_seq = self.remote_seq.wrapping_add(1);
+        self.outgoing(TCP_ACK, &[]);
+    }
+
+    /// Records a SYN answered with a reset.
+    pub fn refused(&mut self) {
+        self.outgoing(TCP_SYN, &[]);
+        self.local_seq = self.local_seq.wrapping_add(1);
+        self.incoming(TCP_RST | TCP_ACK, &[]);
+        self.closed = true;
+    }
+
+    /// Records a SYN that was never answered.
+    pub fn unanswered(&mut self) {
+        self.outgoing(TCP_SYN, &[]);
+        self.closed = true;
+    }
+
+    /// Records data written to the connection.
+    pub fn sent(&mut self, data: &[u8]) {
+        self.outgoing(TCP_PSH | TCP_ACK, data);
+        self.local_seq = self.local_seq.wrapping_add(data.len() as u32);
+    }
+
+    /// Records data read from the connection.
+    pub fn received(&mut self, data: &[u8]) {
+        self.incoming(TCP_PSH | TCP_ACK, data);
+        self.remote_seq = self.remote_seq.wrapping_add(data.len() as u32);
+    }
+
+    /// Records our side closing the connection. Later calls do nothing.
+    pub fn close(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        self.outgoing(TCP_FIN | TCP_ACK, &[]);
+    }
+
+    fn outgoing(&self, flags: u8, data: &[u8]) {
+        let ack = if flags & TCP_ACK != 0 { self.remote_seq } else { 0 };
+        self.capture.write_packet(&packet::tcp(self.local, self.remote, self.local_seq, ack, flags, data));
+    }
+
+    fn incoming(&self, flags: u8, data: &[u8]) {
+        self.capture.write_packet(&packet::tcp(self.remote, self.local, self.remote_seq, self.local_seq, flags, data));
+    }
+}
+
+/// A stream that records what is read from and written to it in a
+/// [`TcpFlow`]. Without a flow it only passes data through.
+pub struct CapturedStream<S> {
+    inner: S,
+    flow: Option<TcpFlow>,
+}
+
+impl<S> CapturedStream<S> {
+    pub fn new(inner: S, flow: Option<TcpFlow>) -> Self {
+        Self { inner, flow }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S> Drop for CapturedStream<S> {
+    fn drop(&mut self) {
+        if let Some(flow) = &mut self.flow {
+            flow.close();
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CapturedStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let (Poll::Ready(Ok(())), Some(flow)) = (&result, &mut this.flow)
+            && buf.filled().len() > before
+        {
+            flow.received(&buf.filled()[before..]);
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CapturedStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let (Poll::Ready(Ok(written)), Some(flow)) = (&result, &mut this.flow) {
+            flow.sent(&buf[..*written]);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.inner).poll_shutdown(cx);
+        if let (Poll::Ready(Ok(())), Some(flow)) = (&result, &mut this.flow) {
+            flow.close();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("probe-capture-{}-{}.pcap", name, std::process::id()))
+    }
+
+    /// Splits a pcap file into its global header and packet records.
+    fn records(bytes: &[u8]) -> Vec<&[u8]> {
+        let mut records = Vec::new();
+        let mut pos = 24;
+        while pos < bytes.len() {
+            let len = u32::from_le_bytes(bytes[pos + 8..pos + 12].try_into().unwrap()) as usize;
+            records.push(&bytes[pos + 16..pos + 16 + len]);
+            pos += 16 + len;
+        }
+        records
+    }
+
+    #[test]
+    fn writes_a_raw_ip_pcap() {
+        let path = temp_path("udp");
+        let capture = Capture::create(&path).unwrap();
+        let local: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let remote: SocketAddr = "10.0.0.1:5520".parse().unwrap();
+        capture.udp(local, remote, &[1; 1200]);
+        capture.udp(remote, local, &[2; 40]);
+        drop(capture);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[..4], &PCAP_MAGIC.to_le_bytes());
+        assert_eq!(&bytes[20..24], &LINKTYPE_RAW.to_le_bytes());
+        let records = records(&bytes);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].len(), 20 + 8 + 1200);
+        assert_eq!(&records[1][12..16], &[10, 0, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn captured_stream_records_a_tcp_flow() {
+        let path = temp_path("tcp");
+        let capture = Capture::create(&path).unwrap();
+        let local: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+        let remote: SocketAddr = "10.0.0.1:7777".parse().unwrap();
+
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut flow = capture.tcp_flow(local, remote);
+        flow.established();
+        let mut stream = CapturedStream::new(client, Some(flow));
+
+        stream.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(b"world!").await.unwrap();
+        let mut buf = [0; 6];
+        stream.read_exact(&mut buf).await.unwrap();
+        drop(stream);
+        drop(capture);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records = records(&bytes);
+        // SYN, SYN-ACK, ACK, data out, data in, FIN
+        let flags: Vec<u8> = records.iter().map(|r| r[20 + 13]).collect();
+        assert_eq!(flags, vec![TCP_SYN, TCP_SYN | TCP_ACK, TCP_ACK, TCP_PSH | TCP_ACK, TCP_PSH | TCP_ACK, TCP_FIN | TCP_ACK]);
+        assert_eq!(&records[3][40..], b"hello");
+        assert_eq!(&records[4][40..], b"world!");
+
+        // The FIN follows the 5 bytes we sent, and acknowledges the 6 we read
+        let seq = |r: &[u8]| u32::from_be_bytes(r[24..28].try_into().unwrap());
+        let ack = |r: &[u8]| u32::from_be_bytes(r[28..32].try_into().unwrap());
+        assert_eq!(seq(records[5]), seq(records[3]).wrapping_add(5));
+        assert_eq!(ack(records[5]), seq(records[4]).wrapping_add(6));
+    }
+
+    #[test]
+    fn wildcard_local_address_is_resolved() {
+        let local = local_addr_for("0.0.0.0:40000".parse().unwrap(), "127.0.0.1:5520".parse().unwrap());
+        assert_eq!(local, "127.0.0.1:40000".parse().unwrap());
+    }
+}
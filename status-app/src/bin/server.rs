@@ -1,6 +1,5 @@
 #[cfg(not(target_arch = "wasm32"))]
 mod server_impl {
-    use std::env;
     use std::path::PathBuf;
 
     use std::time::Duration;
@@ -14,8 +13,8 @@ mod server_impl {
     };
     use futures::stream::Stream;
     use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-    use status_monitor::{run_monitor, MonitorConfig};
-    use status_app::models::PublicConfig;
+    use status_monitor::{load_latest, run_monitor, MonitorConfig};
+    use status_app::models::{PublicConfig, PublicService, ServiceStatus};
     use tokio::sync::watch;
     use tokio_stream::wrappers::WatchStream;
     use tokio_stream::StreamExt;
@@ -23,14 +22,26 @@ mod server_impl {
     use tower_http::cors::CorsLayer;
 
     pub async fn main() -> Result<()> {
-        let config = load_config();
+        // status-monitor.toml (or the file named by MONITOR_CONFIG), else the
+        // TERRARIA_* / HYTALE_* environment variables
+        let config = MonitorConfig::load()?;
 
         // Prepare public config
         let public_config = PublicConfig {
-            terraria: format!("{}:{}", config.terraria_host, config.terraria_port),
-            hytale: format!("{}:{}", config.hytale_host, config.hytale_port),
+            services: config
+                .services
+                .iter()
+                .map(|service| PublicService {
+                    id: service.id.clone(),
+                    name: service.name.clone(),
+                    kind: service.checker.kind().to_string(),
+                    address: service.address(),
+                })
+                .collect(),
         };
 
+        let latest_path = config.latest_path();
+
         // Start background tasks
         spawn_monitor(config.clone());
         let rx = spawn_file_watcher(config.history_path.clone()).await;
@@ -39,6 +50,7 @@ mod server_impl {
         let app = Router::new()
             .route("/api/sse", get(sse_handler))
             .route("/api/config", get(move || async move { Json(public_config) }))
+            .route("/api/latest", get(move || latest_handler(latest_path.clone())))
             .with_state(rx)
             .fallback_service(ServeDir::new("dist"))
             .layer(CorsLayer::permissive());
@@ -50,32 +62,6 @@ mod server_impl {
         Ok(())
     }
 
-    fn load_config() -> MonitorConfig {
-        let terraria_host = env::var("TERRARIA_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let terraria_port = env::var("TERRARIA_PORT")
-            .ok()
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(7777);
-
-        let hytale_host = env::var("HYTALE_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let hytale_port = env::var("HYTALE_PORT")
-            .ok()
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(12345);
-
-        let history_path = env::var("HISTORY_FILE_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("status-history.csv"));
-
-        MonitorConfig {
-            terraria_host,
-            terraria_port,
-            hytale_host,
-            hytale_port,
-            history_path,
-        }
-    }
-
     fn spawn_monitor(config: MonitorConfig) {
         tokio::spawn(async move {
             loop {
@@ -129,6 +115,11 @@ mod server_impl {
         rx
     }
 
+    async fn latest_handler(path: PathBuf) -> Json<Vec<ServiceStatus>> {
+        // Missing or half-written file just means no details yet
+        Json(load_latest(&path).await.unwrap_or_default())
+    }
+
     async fn sse_handler(
         State(rx): State<watch::Receiver<String>>,
     ) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
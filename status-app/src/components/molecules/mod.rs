@@ -1,3 +1,3 @@
 pub mod server_address;
 pub mod history_chart;
-
+pub mod server_details;
@@ -0,0 +1,24 @@
+use leptos::prelude::*;
+
+#[allow(non_snake_case)]
+#[component]
+pub fn ServerDetails(
+    #[prop(into)] details: Signal<Vec<(String, String)>>,
+) -> impl IntoView {
+    let rows = move || {
+        details.get().into_iter().map(|(label, value)| {
+            view! {
+                <div class="flex items-baseline gap-2">
+                    <span class="text-yellow-light">{label}</span>
+                    <span>{value}</span>
+                </div>
+            }
+        }).collect::<Vec<_>>()
+    };
+
+    view! {
+        <div class="flex flex-wrap gap-x-6 gap-y-1 mb-3 text-lg font-semibold text-white text-stroke-sm [paint-order:stroke_fill] drop-shadow-brown-sm empty:hidden">
+            {rows}
+        </div>
+    }
+}
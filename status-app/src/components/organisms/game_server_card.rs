@@ -1,5 +1,6 @@
 use leptos::prelude::*;
 use crate::components::molecules::server_address::ServerAddress;
+use crate::components::molecules::server_details::ServerDetails;
 use crate::config::GameCardConfig;
 use crate::components::atoms::bananil_spinner::BananilSpinner;
 use crate::components::atoms::status_indicator::{StatusIndicator, Status};
@@ -9,10 +10,12 @@ use crate::components::atoms::stat_display::StatDisplay;
 #[component]
 pub fn GameServerCard(
     #[prop(into)] config: GameCardConfig,
+    #[prop(into)] name: String,
     #[prop(into)] online: Signal<Option<bool>>,
     #[prop(into)] ping: Signal<String>,
     #[prop(into)] uptime: Signal<String>,
     #[prop(into)] uri: Signal<String>,
+    #[prop(into)] details: Signal<Vec<(String, String)>>,
 ) -> impl IntoView {
     let inline_style = format!("background-image: url('{}')", config.background_image);
 
@@ -24,7 +27,7 @@ pub fn GameServerCard(
             // Header
             <div class="flex justify-between items-center mb-3">
                 <h2 class="text-4xl font-semibold text-white text-stroke-server [paint-order:stroke_fill] drop-shadow-brown-md">
-                    {config.name}
+                    {name}
                 </h2>
                 {move || match online.get() {
                     Some(true) => view! {
@@ -51,6 +54,9 @@ pub fn GameServerCard(
                 </div>
             </div>
 
+            // Extra probe details (version, world, ...)
+            <ServerDetails details=details />
+
             // URI
             <ServerAddress uri=uri />
         </div>
@@ -2,58 +2,16 @@ use leptos::prelude::*;
 use crate::components::organisms::header::Header;
 use crate::components::organisms::game_server_card::GameServerCard;
 use crate::components::organisms::footer::Footer;
-use crate::models::{History, PublicConfig};
-use crate::config::{TERRARIA_CONFIG, HYTALE_CONFIG};
+use std::collections::BTreeMap;
+
+use crate::models::{History, PublicConfig, PublicService, ServiceStatus};
+use crate::config::card_config;
 use web_sys::{EventSource, MessageEvent};
 use wasm_bindgen::prelude::*;
 use gloo_net::http::Request;
-use chrono::DateTime;
 
 use crate::components::atoms::bananil_spinner::BananilSpinner;
 
-fn parse_history_from_csv(csv_str: &str) -> History {
-    let mut history = History::default();
-    for (i, line) in csv_str.lines().enumerate() {
-        if i == 0 { continue; }
-        if line.trim().is_empty() { continue; }
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() >= 5 {
-            if let Ok(ts) = DateTime::parse_from_rfc3339(parts[0]) {
-                let timestamp = ts.with_timezone(&chrono::Utc);
-                // Terraria
-                let t_online = match parts[1].trim() {
-                    "true" => Some(true),
-                    "false" => Some(false),
-                    _ => None,
-                };
-                let t_latency = parts[2].parse().unwrap_or(0);
-
-                // Hytale
-                let h_online = match parts[3].trim() {
-                    "true" => Some(true),
-                    "false" => Some(false),
-                    _ => None,
-                };
-                let h_latency = parts[4].parse().unwrap_or(0);
-
-                history.terraria.push(crate::models::ServiceStatus {
-                    service_name: "Terraria".to_string(),
-                    is_online: t_online,
-                    latency_ms: t_latency,
-                    timestamp,
-                });
-                history.hytale.push(crate::models::ServiceStatus {
-                    service_name: "Hytale".to_string(),
-                    is_online: h_online,
-                    latency_ms: h_latency,
-                    timestamp,
-                });
-            }
-        }
-    }
-    history
-}
-
 fn calculate_service_stats(entries: &[crate::models::ServiceStatus]) -> (String, String, Option<bool>, usize, usize) {
     if entries.is_empty() {
         return ("---".to_string(), "---".to_string(), Some(false), 0, 0);
@@ -78,18 +36,78 @@ fn calculate_service_stats(entries: &[crate::models::ServiceStatus]) -> (String,
     (uptime, ping, status, total, online)
 }
 
+/// Label/value pairs shown on a server card from the latest full check.
+fn service_details(status: &ServiceStatus) -> Vec<(String, String)> {
+    let mut details = Vec::new();
+    if let Some(failure) = &status.failure {
+        details.push(("Failure:".to_string(), failure.clone()));
+    }
+    // Only worth a row each when there is more than one address to tell apart
+    if status.addresses.len() > 1 {
+        for address in &status.addresses {
+            let state = match &address.failure {
+                _ if address.is_online => format!("OK · {}ms", address.latency_ms),
+                Some(failure) => format!("down · {}", failure),
+                None => "down".to_string(),
+            };
+            details.push((format!("{} {}:", address.family, address.address), state));
+        }
+    }
+    if let Some(version) = &status.version {
+        details.push(("Client version:".to_string(), version.clone()));
+    }
+    if let Some(loss) = status.loss_percent {
+        let jitter = status.jitter_ms.map(|j| format!(" · jitter {:.1}ms", j)).unwrap_or_default();
+        details.push(("Packet loss:".to_string(), format!("{:.0}%{}", loss, jitter)));
+    }
+    if let Some(mtu) = &status.mtu {
+        let value = match mtu.largest_answered {
+            _ if mtu.problem => format!("⚠️ MTU problem · {}", mtu.detail),
+            Some(size) => format!("{} bytes", size),
+            None => mtu.detail.clone(),
+        };
+        details.push(("MTU:".to_string(), value));
+    }
+    if let Some(retry) = &status.retry {
+        let value = match retry.final_rtt_ms {
+            Some(final_rtt) => format!("⚠️ Retry in {}ms · resent Initial {}ms", retry.retry_rtt_ms, final_rtt),
+            None => format!("⚠️ Retry in {}ms · resent Initial unanswered", retry.retry_rtt_ms),
+        };
+        details.push(("Under load:".to_string(), value));
+    }
+    if let Some(handshake_ms) = status.handshake_ms {
+        details.push(("Handshake:".to_string(), format!("{}ms", handshake_ms)));
+    }
+    if let Some(cert) = &status.certificate {
+        details.push(("Certificate:".to_string(), cert.subject.clone()));
+        let expiry = format!("{} ({} days)", cert.expires.format("%Y-%m-%d"), cert.expires_in_days);
+        let expiry = if cert.expires_soon() { format!("⚠️ {}", expiry) } else { expiry };
+        details.push(("Cert expires:".to_string(), expiry));
+    }
+    if let Some(world) = &status.world {
+        details.push(("World:".to_string(), format!("{} ({})", world.name, world.size)));
+        details.push(("Time:".to_string(), format!("{} {}", world.time, if world.is_day { "☀️" } else { "🌙" })));
+        let mode = match world.hardmode {
+            Some(true) => format!("{} · Hardmode", world.difficulty),
+            _ => world.difficulty.clone(),
+        };
+        details.push(("Mode:".to_string(), mode));
+        if !world.events.is_empty() {
+            details.push(("Events:".to_string(), world.events.join(", ")));
+        }
+    }
+    details
+}
+
+/// One bar per minute, online only if every service checked in that minute was.
 fn generate_history_bars(history: &History) -> Vec<(i64, bool)> {
-    let len = std::cmp::min(history.terraria.len(), history.hytale.len());
-    let mut bars = Vec::with_capacity(len);
-    for i in 0..len {
-         let t = &history.terraria[i];
-         let h = &history.hytale[i];
-         let t_status = t.is_online.unwrap_or(true);
-         let h_status = h.is_online.unwrap_or(true);
-         let ts = t.timestamp.timestamp_millis();
-         bars.push((ts, t_status && h_status));
+    let mut bars = BTreeMap::new();
+    for entry in history.services.values().flatten() {
+        let minute = entry.timestamp.timestamp_millis() / 60_000 * 60_000;
+        let online = bars.entry(minute).or_insert(true);
+        *online &= entry.is_online.unwrap_or(true);
     }
-    bars
+    bars.into_iter().collect()
 }
 
 #[allow(non_snake_case)]
@@ -105,14 +123,9 @@ pub fn Home() -> impl IntoView {
         None
     });
 
-    // Signals to store the latest status
-    let (terraria_online, set_terraria_online) = signal(Option::<bool>::None);
-    let (terraria_ping, set_terraria_ping) = signal("---".to_string());
-    let (terraria_uptime, set_terraria_uptime) = signal("---".to_string());
-
-    let (hytale_online, set_hytale_online) = signal(Option::<bool>::None);
-    let (hytale_ping, set_hytale_ping) = signal("---".to_string());
-    let (hytale_uptime, set_hytale_uptime) = signal("---".to_string());
+    // Check history of every service, and the latest full check of each
+    let (history, set_history) = signal(History::default());
+    let (latest, set_latest) = signal(Vec::<ServiceStatus>::new());
 
     let (total_uptime, set_total_uptime) = signal("---".to_string());
     // Vec<(timestamp_ms, is_online)>
@@ -128,24 +141,14 @@ pub fn Home() -> impl IntoView {
                 set_has_loaded_history.set(true);
                 let csv_str: String = txt.into();
 
-                // Use helper to parse
-                let history = parse_history_from_csv(&csv_str);
-
-                // Update Terraria
-                let (t_uptime, t_ping, t_status, t_checks, t_online) = calculate_service_stats(&history.terraria);
-                set_terraria_uptime.set(t_uptime);
-                set_terraria_ping.set(t_ping);
-                set_terraria_online.set(t_status);
-
-                // Update Hytale
-                let (h_uptime, h_ping, h_status, h_checks, h_online) = calculate_service_stats(&history.hytale);
-                set_hytale_uptime.set(h_uptime);
-                set_hytale_ping.set(h_ping);
-                set_hytale_online.set(h_status);
+                let history = History::from_csv(&csv_str).unwrap_or_default();
 
                 // Total Uptime
-                let total_checks = t_checks + h_checks;
-                let total_online = t_online + h_online;
+                let (total_checks, total_online) = history
+                    .services
+                    .values()
+                    .map(|entries| calculate_service_stats(entries))
+                    .fold((0, 0), |(checks, online), stats| (checks + stats.3, online + stats.4));
                 if total_checks > 0 {
                     let pct = (total_online as f64 / total_checks as f64) * 100.0;
                     set_total_uptime.set(format!("{:.2}%", pct));
@@ -154,8 +157,17 @@ pub fn Home() -> impl IntoView {
                 }
 
                 // History Bars
-                let bars = generate_history_bars(&history);
-                set_history_bars.set(bars);
+                set_history_bars.set(generate_history_bars(&history));
+                set_history.set(history);
+
+                // Details only live in the latest-status file, refresh them with each update
+                leptos::task::spawn_local(async move {
+                    if let Ok(resp) = Request::get("/api/latest").send().await
+                        && let Ok(latest) = resp.json::<Vec<ServiceStatus>>().await
+                    {
+                        set_latest.set(latest);
+                    }
+                });
             }
         });
 
@@ -163,6 +175,8 @@ pub fn Home() -> impl IntoView {
         onmessage.forget(); // Keep the closure alive
     });
 
+    let services = move || config.get().flatten().map(|c| c.services).unwrap_or_default();
+
     view! {
         // Outer wrapper simulating body
         <div class="min-h-screen flex flex-col items-center py-16 px-4 font-fredoka relative before:content-[''] before:fixed before:top-0 before:left-0 before:w-full before:h-full before:bg-banana-bg before:bg-banana-tile before:bg-repeat before:bg-[length:800px] before:opacity-80 before:-z-10 before:pointer-events-none">
@@ -173,19 +187,30 @@ pub fn Home() -> impl IntoView {
 
                 // Server Cards Container
                 <div class="grid grid-cols-1 min-[900px]:grid-cols-2 gap-6 mb-8 mt-4 md:mt-14">
-                    <GameServerCard
-                        config=TERRARIA_CONFIG
-                        online=terraria_online
-                        ping=terraria_ping
-                        uptime=terraria_uptime
-                        uri=move || config.get().flatten().map(|c| c.terraria.clone()).unwrap_or("...".to_string())
-                    />
-                    <GameServerCard
-                        config=HYTALE_CONFIG
-                        online=hytale_online
-                        ping=hytale_ping
-                        uptime=hytale_uptime
-                        uri=move || config.get().flatten().map(|c| c.hytale.clone()).unwrap_or("...".to_string())
+                    <For
+                        each=services
+                        key=|service| service.id.clone()
+                        children=move |service: PublicService| {
+                            let id = service.id.clone();
+                            let stats = Memo::new(move |_| history.with(|history| calculate_service_stats(history.get(&id))));
+                            let id = service.id.clone();
+                            let details = Signal::derive(move || {
+                                latest.with(|latest| {
+                                    latest.iter().find(|status| status.service_id == id).map(service_details).unwrap_or_default()
+                                })
+                            });
+                            view! {
+                                <GameServerCard
+                                    config=card_config(&service.kind)
+                                    name=service.name
+                                    online=Signal::derive(move || if has_loaded_history.get() { stats.get().2 } else { None })
+                                    ping=Signal::derive(move || stats.get().1)
+                                    uptime=Signal::derive(move || stats.get().0)
+                                    uri=service.address
+                                    details=details
+                                />
+                            }
+                        }
                     />
                 </div>
 
@@ -19,3 +19,11 @@ pub const HYTALE_CONFIG: GameCardConfig = GameCardConfig {
     border_class: "border-hytale",
     logo: "images/hytale-logo.png",
 };
+
+/// Card look for a service checked by the `kind` checker.
+pub fn card_config(kind: &str) -> GameCardConfig {
+    match kind {
+        "hytale" => HYTALE_CONFIG,
+        _ => TERRARIA_CONFIG,
+    }
+}
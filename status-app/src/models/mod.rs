@@ -4,6 +4,17 @@ pub use status_monitor::{ServiceStatus, History};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct PublicConfig {
-    pub terraria: String,
-    pub hytale: String,
+    /// The monitored services, in config order.
+    pub services: Vec<PublicService>,
+}
+
+/// What the dashboard shows of a monitored service.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PublicService {
+    pub id: String,
+    pub name: String,
+    /// Checker kind (`terraria`, `hytale`), which picks the card's look.
+    pub kind: String,
+    /// `host:port` players connect to.
+    pub address: String,
 }
@@ -0,0 +1,454 @@
+//! The services the monitor checks, read from a TOML file:
+//!
+//! ```toml
+//! history_path = "/data/status-history.csv"
+//!
+//! [[service]]
+//! id = "terraria"
+//! name = "Terraria"
+//! kind = "terraria"
+//! host = "play.bananil.net"
+//! port = 7777
+//! interval_secs = 60
+//! timeout_secs = 3
+//! # Off by default: logs in as a guest every check to read the world
+//! deep_probe = true
+//!
+//! [[service]]
+//! id = "hytale"
+//! name = "Hytale"
+//! kind = "hytale"
+//! host = "play.bananil.net"
+//! port = 5520
+//! strategy = "initial"
+//! alpn = ["hytale/1"]
+//! burst = 5
+//!
+//! # Optional, with the `sqlite` feature
+//! [sqlite]
+//! path = "/data/status-history.db"
+//! retention_days = 365
+//! ```
+//!
+//! Without a config file the monitor falls back to the environment variables
+//! it has always read (`TERRARIA_HOST`, `HYTALE_PORT`, ...), which describe
+//! one Terraria and one Hytale server.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Deserializer};
+
+use crate::{ProbeOptions, CHECK_INTERVAL_SECS, DEFAULT_HYTALE_BURST};
+
+/// Environment variable naming the config file.
+pub const CONFIG_PATH_VAR: &str = "MONITOR_CONFIG";
+/// Config file read when [`CONFIG_PATH_VAR`] is unset, if it exists.
+pub const DEFAULT_CONFIG_PATH: &str = "status-monitor.toml";
+/// Seconds a Terraria check may take unless configured otherwise: what the
+/// monitor always allowed for the connection.
+pub const DEFAULT_TERRARIA_TIMEOUT_SECS: u64 = 3;
+/// Seconds a Hytale check may take unless configured otherwise: the probe's own reply timeout.
+pub const DEFAULT_HYTALE_TIMEOUT_SECS: u64 = hytale_health_checker::PROBE_TIMEOUT.as_secs();
+/// Days of checks the SQLite history keeps unless configured otherwise.
+pub const DEFAULT_RETENTION_DAYS: u64 = 365;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonitorConfig {
+    #[serde(default = "default_history_path")]
+    pub history_path: PathBuf,
+    /// Also record every check in a SQLite database. Needs the `sqlite` feature.
+    #[serde(default)]
+    pub sqlite: Option<SqliteConfig>,
+    #[serde(rename = "service")]
+    pub services: Vec<ServiceConfig>,
+}
+
+/// One game server to check. Keys that are neither the service's nor its
+/// checker's are rejected by [`CheckerConfig`], which sees every key left over.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceConfig {
+    /// Stable key of the service in the monitor's files (e.g. `terraria-2`):
+    /// ASCII letters, digits, `_` and `-` only.
+    pub id: String,
+    /// Name shown on the dashboard.
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Upper bound for one check, after which the server counts as timed out.
+    /// Also the checker's own connect and reply timeouts. Defaults to the
+    /// checker's [`CheckerConfig::default_timeout_secs`].
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Check every address the host resolves to, so IPv4 and IPv6 are reported separately.
+    #[serde(default)]
+    pub probe_all_addresses: bool,
+    /// Which checker to run, with its own options.
+    #[serde(flatten)]
+    pub checker: CheckerConfig,
+}
+
+/// Where the SQLite history lives and how long it keeps checks.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SqliteConfig {
+    pub path: PathBuf,
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+impl SqliteConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), retention_days: DEFAULT_RETENTION_DAYS }
+    }
+}
+
+/// The checker of a service and its options, selected by the `kind` key.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum CheckerConfig {
+    Terraria {
+        /// Log in as a guest each check to record world details.
+        #[serde(default)]
+        deep_probe: bool,
+    },
+    Hytale {
+        /// `initial` (default) or `version-negotiation`.
+        #[serde(default, deserialize_with = "from_str")]
+        strategy: hytale_health_checker::ProbeStrategy,
+        /// ClientHello SNI; defaults to the host.
+        #[serde(default)]
+        sni: Option<String>,
+        /// ALPN protocols offered with the Initial strategy.
+        #[serde(default)]
+        alpn: Vec<String>,
+        /// Packets per check, so a single lost datagram doesn't mark the server offline.
+        #[serde(default = "default_burst")]
+        burst: usize,
+        /// Complete a QUIC handshake each check, to time it and watch the
+        /// certificate's expiry.
+        #[serde(default)]
+        deep_probe: bool,
+    },
+}
+
+impl MonitorConfig {
+    /// Reads the config file named by `MONITOR_CONFIG`, or `status-monitor.toml`
+    /// when it exists, and falls back to the environment variables otherwise.
+    pub fn load() -> Result<Self> {
+        match env::var(CONFIG_PATH_VAR) {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_PATH)),
+            Err(_) => {
+                let config = Self::from_env();
+                config.validate()?;
+                Ok(config)
+            }
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read config {}", path.display()))?;
+        content.parse().with_context(|| format!("Invalid config {}", path.display()))
+    }
+
+    /// One Terraria and one Hytale service, described by the environment
+    /// variables the monitor read before it had a config file.
+    pub fn from_env() -> Self {
+        let terraria_host = env::var("TERRARIA_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let terraria_port = env::var("TERRARIA_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(7777);
+
+        let hytale_host = env::var("HYTALE_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let hytale_port = env::var("HYTALE_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(12345);
+
+        // Only the first address that connects is checked unless enabled
+        let probe_all_addresses = env::var("PROBE_ALL_ADDRESSES")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let terraria = CheckerConfig::Terraria {
+            // Logging in as a guest is off unless enabled
+            deep_probe: env::var("TERRARIA_DEEP_PROBE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+        };
+
+        let hytale = CheckerConfig::Hytale {
+            strategy: env::var("HYTALE_PROBE_STRATEGY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            sni: env::var("HYTALE_SNI").ok(),
+            // Comma-separated
+            alpn: env::var("HYTALE_ALPN")
+                .map(|v| v.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+                .unwrap_or_default(),
+            burst: env::var("HYTALE_PROBE_BURST")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_HYTALE_BURST),
+            // Handshake and certificate checks are off unless enabled
+            deep_probe: env::var("HYTALE_DEEP_PROBE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+        };
+
+        let history_path = env::var("HISTORY_FILE_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| default_history_path());
+
+        MonitorConfig {
+            history_path,
+            sqlite: env::var("SQLITE_PATH").ok().map(SqliteConfig::new),
+            services: vec![
+                ServiceConfig::new("terraria", "Terraria", &terraria_host, terraria_port, terraria)
+                    .probe_all_addresses(probe_all_addresses),
+                ServiceConfig::new("hytale", "Hytale", &hytale_host, hytale_port, hytale)
+                    .probe_all_addresses(probe_all_addresses),
+            ],
+        }
+    }
+
+    /// Where the latest full check results are written, alongside the history file.
+    pub fn latest_path(&self) -> PathBuf {
+        self.history_path.with_file_name(crate::LATEST_FILE_NAME)
+    }
+}
+
+impl FromStr for MonitorConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: MonitorConfig = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl MonitorConfig {
+    fn validate(&self) -> Result<()> {
+        if self.services.is_empty() {
+            bail!("no services configured");
+        }
+        for (i, service) in self.services.iter().enumerate() {
+            // Ids go unquoted into the history file's rows
+            if service.id.is_empty() {
+                bail!("a service has an empty id");
+            }
+            if !service.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                bail!("service id '{}' may only contain letters, digits, '_' and '-'", service.id.escape_debug());
+            }
+            if self.services[..i].iter().any(|other| other.id == service.id) {
+                bail!("service id '{}' is used more than once", service.id);
+            }
+            if service.interval_secs == 0 {
+                bail!("service '{}' has an interval of 0 seconds", service.id);
+            }
+            if service.timeout_secs == Some(0) {
+                bail!("service '{}' has a timeout of 0 seconds", service.id);
+            }
+        }
+        if self.sqlite.is_some() && !cfg!(feature = "sqlite") {
+            bail!("a SQLite history is configured, but status-monitor was built without the `sqlite` feature");
+        }
+        Ok(())
+    }
+}
+
+impl ServiceConfig {
+    /// A service with the default interval and timeout, checking the first address that answers.
+    pub fn new(id: &str, name: &str, host: &str, port: u16, checker: CheckerConfig) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+            interval_secs: CHECK_INTERVAL_SECS,
+            timeout_secs: None,
+            probe_all_addresses: false,
+            checker,
+        }
+    }
+
+    pub fn probe_all_addresses(mut self, probe_all_addresses: bool) -> Self {
+        self.probe_all_addresses = probe_all_addresses;
+        self
+    }
+
+    /// `host:port`, as players enter it.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or_else(|| self.checker.default_timeout_secs()))
+    }
+}
+
+impl CheckerConfig {
+    /// The `kind` key selecting this checker.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheckerConfig::Terraria { .. } => "terraria",
+            CheckerConfig::Hytale { .. } => "hytale",
+        }
+    }
+
+    /// Seconds a check may take when the service doesn't set `timeout_secs`.
+    pub fn default_timeout_secs(&self) -> u64 {
+        match self {
+            CheckerConfig::Terraria { .. } => DEFAULT_TERRARIA_TIMEOUT_SECS,
+            CheckerConfig::Hytale { .. } => DEFAULT_HYTALE_TIMEOUT_SECS,
+        }
+    }
+
+    /// Options for the Hytale probe; `None` for other checkers.
+    pub fn hytale_probe(&self) -> Option<ProbeOptions> {
+        match self {
+            CheckerConfig::Hytale { strategy, sni, alpn, burst, .. } => Some(ProbeOptions {
+                strategy: *strategy,
+                server_name: sni.clone(),
+                alpn: alpn.clone(),
+                burst: *burst,
+                ..Default::default()
+            }),
+            CheckerConfig::Terraria { .. } => None,
+        }
+    }
+}
+
+fn default_history_path() -> PathBuf {
+    PathBuf::from("status-history.csv")
+}
+
+fn default_interval_secs() -> u64 {
+    CHECK_INTERVAL_SECS
+}
+
+fn default_retention_days() -> u64 {
+    DEFAULT_RETENTION_DAYS
+}
+
+fn default_burst() -> usize {
+    DEFAULT_HYTALE_BURST
+}
+
+/// Deserializes a string through the type's `FromStr`.
+fn from_str<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_are_read_from_toml() {
+        let config: MonitorConfig = r#"
+            history_path = "/data/status-history.csv"
+
+            [[service]]
+            id = "terraria"
+            name = "Terraria"
+            kind = "terraria"
+            host = "play.bananil.net"
+            port = 7777
+            deep_probe = true
+
+            [[service]]
+            id = "terraria-2"
+            name = "Terraria (Expert)"
+            kind = "terraria"
+            host = "play.bananil.net"
+            port = 7778
+            interval_secs = 120
+
+            [[service]]
+            id = "hytale"
+            name = "Hytale"
+            kind = "hytale"
+            host = "play.bananil.net"
+            port = 5520
+            timeout_secs = 10
+            strategy = "version-negotiation"
+            alpn = ["hytale/1"]
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.history_path, PathBuf::from("/data/status-history.csv"));
+        assert_eq!(config.services.len(), 3);
+        assert!(matches!(config.services[0].checker, CheckerConfig::Terraria { deep_probe: true }));
+        assert!(matches!(config.services[1].checker, CheckerConfig::Terraria { deep_probe: false }));
+        assert_eq!(config.services[1].interval(), Duration::from_secs(120));
+        assert!(!config.services[1].probe_all_addresses);
+        assert_eq!(config.services[0].interval(), Duration::from_secs(CHECK_INTERVAL_SECS));
+        assert_eq!(config.services[2].timeout(), Duration::from_secs(10));
+        // Each checker keeps its own timeout unless told otherwise
+        assert_eq!(config.services[0].timeout(), Duration::from_secs(DEFAULT_TERRARIA_TIMEOUT_SECS));
+        assert_eq!(ServiceConfig::new("h", "H", "h", 1, config.services[2].checker.clone()).timeout().as_secs(), 5);
+        let probe = config.services[2].checker.hytale_probe().unwrap();
+        assert_eq!(probe.strategy, hytale_health_checker::ProbeStrategy::VersionNegotiation);
+        assert_eq!(probe.alpn, vec!["hytale/1"]);
+        assert_eq!(probe.burst, DEFAULT_HYTALE_BURST);
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let service = |id: &str, kind: &str| {
+            format!("[[service]]\nid = \"{}\"\nname = \"x\"\nkind = \"{}\"\nhost = \"h\"\nport = 1\n", id, kind)
+        };
+
+        assert!(service("a", "minecraft").parse::<MonitorConfig>().is_err());
+        assert!(format!("{}{}", service("a", "terraria"), service("a", "hytale")).parse::<MonitorConfig>().is_err());
+        assert!("history_path = \"h.csv\"\nservice = []".parse::<MonitorConfig>().is_err());
+        // Ids that would break the history file's rows
+        assert!(service("", "terraria").parse::<MonitorConfig>().is_err());
+        assert!(service("a,b", "terraria").parse::<MonitorConfig>().is_err());
+        assert!(service("a\\nb", "terraria").parse::<MonitorConfig>().is_err());
+        assert!(service("a b", "terraria").parse::<MonitorConfig>().is_err());
+        assert!(service("télé", "terraria").parse::<MonitorConfig>().is_err());
+        assert!(service("Terraria_2-expert", "terraria").parse::<MonitorConfig>().is_ok());
+        assert!(format!("{}interval_secs = 0\n", service("a", "terraria")).parse::<MonitorConfig>().is_err());
+        assert!(format!("{}timeout_secs = 0\n", service("a", "hytale")).parse::<MonitorConfig>().is_err());
+        // Typos are errors rather than silently ignored
+        assert!(format!("{}deep_prob = true\n", service("a", "terraria")).parse::<MonitorConfig>().is_err());
+        assert!(format!("{}burts = 3\n", service("a", "hytale")).parse::<MonitorConfig>().is_err());
+        assert!(format!("histroy_path = \"h.csv\"\n{}", service("a", "terraria")).parse::<MonitorConfig>().is_err());
+        assert!(format!("{}deep_probe = true\n", service("a", "terraria")).parse::<MonitorConfig>().is_ok());
+        assert!(service("a", "terraria").parse::<MonitorConfig>().is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "sqlite"))]
+    fn sqlite_history_needs_the_feature() {
+        let config = "[[service]]\nid = \"a\"\nname = \"x\"\nkind = \"terraria\"\nhost = \"h\"\nport = 1\n\n\
+                      [sqlite]\npath = \"h.db\"\n";
+
+        let error = config.parse::<MonitorConfig>().unwrap_err();
+        assert!(error.to_string().contains("without the `sqlite` feature"), "{:#}", error);
+    }
+}
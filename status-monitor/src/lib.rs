@@ -1,226 +1,1000 @@
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::{Path, PathBuf};
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::Duration;
+use std::collections::BTreeMap;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 #[cfg(not(target_arch = "wasm32"))]
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::fs::{self, File};
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::io::AsyncWriteExt;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::task::JoinSet;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::{Instant, MissedTickBehavior};
+#[cfg(not(target_arch = "wasm32"))]
+pub use hytale_health_checker::ProbeOptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use config::{CheckerConfig, MonitorConfig, ServiceConfig, SqliteConfig};
+#[cfg(not(target_arch = "wasm32"))]
+use health_check::{async_trait, CheckResult, HealthCheck, ProbeOutcome};
+#[cfg(not(target_arch = "wasm32"))]
+use hytale_health_checker::HytaleCheck;
+#[cfg(not(target_arch = "wasm32"))]
+use terraria_health_checker::{
+    CheckOptions, DisconnectReason, HandshakeOutcome, ServerInfo, ServerVersion, TerrariaCheck, WorldInfo,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub mod sqlite;
 
 pub const MAX_HISTORY_ENTRIES: usize = 1440;
+/// Rows a service may have beyond [`MAX_HISTORY_ENTRIES`] in the history file
+/// before it is compacted, so the file is rewritten about once an hour
+/// rather than every check.
+pub const HISTORY_COMPACT_SLACK: usize = 60;
+/// Seconds between checks of a service unless configured otherwise.
 pub const CHECK_INTERVAL_SECS: u64 = 60;
+/// Time a check gets beyond its service's timeout before it is abandoned,
+/// so the checker's own timeout, with its more specific failure, fires first.
+#[cfg(not(target_arch = "wasm32"))]
+const TIMEOUT_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
+/// File written next to the history file with the full result of the latest check per service.
+pub const LATEST_FILE_NAME: &str = "status-latest.json";
+/// Header of the history file: one row per check of any service.
+pub const HISTORY_HEADER: &str = "Timestamp,Service,Online,Latency";
+/// Header of history files written before the monitor checked arbitrary services.
+pub const LEGACY_HISTORY_HEADER: &str = "Timestamp,TerrariaOnline,TerrariaLatency,HytaleOnline,HytaleLatency";
+/// Packets the Hytale probe sends per check unless configured otherwise.
+pub const DEFAULT_HYTALE_BURST: usize = 5;
+/// Run the Hytale MTU diagnostic every this many checks (and after an outage).
+pub const MTU_CHECK_EVERY: u32 = 60;
+/// Warn when the Hytale server's certificate expires within this many days.
+pub const CERT_EXPIRY_WARNING_DAYS: i64 = 14;
+/// Wait before looking for a Terraria server's release again after a
+/// discovery that found none; doubles with each such discovery.
+pub const DISCOVERY_RETRY: std::time::Duration = std::time::Duration::from_secs(300);
+/// Longest wait between two discoveries of a Terraria server's release.
+pub const DISCOVERY_RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(6 * 3600);
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Result of one check of a service.
+///
+/// The history file only keeps the service, timestamp, online state and
+/// latency; the other fields are only kept in the latest-status file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct ServiceStatus {
+    /// Id of the service in the monitor's config.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub service_id: String,
     pub service_name: String,
     pub is_online: Option<bool>,
     pub latency_ms: u128,
     pub timestamp: DateTime<Utc>,
+    /// Game version the server accepts (e.g. `1.4.4.9`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// World details from a Terraria deep probe.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world: Option<WorldSummary>,
+    /// Why the check found the server offline (refused, timed out, ...).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    /// Per-address results when every resolved address is checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<AddressStatus>,
+    /// Time to complete a full QUIC handshake, from a Hytale deep probe.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handshake_ms: Option<u128>,
+    /// Server certificate seen by a Hytale deep probe.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<CertificateSummary>,
+    /// Share of the Hytale probe's burst that went unanswered, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loss_percent: Option<f64>,
+    /// Round-trip jitter over the Hytale probe's burst.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<f64>,
+    /// Latest MTU diagnostic for the Hytale port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<MtuSummary>,
+    /// Set when the Hytale server answered the probe with a Retry, as servers under load do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetrySummary>,
+}
+
+/// Check result for one address of a dual-stack or multi-record host.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct AddressStatus {
+    /// `IPv4` or `IPv6`.
+    pub family: String,
+    pub address: String,
+    pub is_online: bool,
+    pub latency_ms: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
 }
 
+/// Outcome of the MTU diagnostic, as recorded by the monitor.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct MtuSummary {
+    /// Largest datagram the server answered, in bytes.
+    pub largest_answered: Option<usize>,
+    /// The path drops datagrams QUIC clients need.
+    pub problem: bool,
+    pub detail: String,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MtuSummary {
+    fn new(report: &hytale_health_checker::MtuReport, checked_at: DateTime<Utc>) -> Self {
+        Self {
+            largest_answered: report.largest_answered,
+            problem: report.is_problem(),
+            detail: report.to_string(),
+            checked_at,
+        }
+    }
+}
+
+/// Round trips of a Hytale probe the server answered with a Retry.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RetrySummary {
+    pub retry_rtt_ms: u128,
+    /// Round trip of the Initial resent with the token; `None` when the server never answered it.
+    pub final_rtt_ms: Option<u128>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&hytale_health_checker::RetryInfo> for RetrySummary {
+    fn from(retry: &hytale_health_checker::RetryInfo) -> Self {
+        Self { retry_rtt_ms: retry.retry_rtt_ms, final_rtt_ms: retry.final_rtt_ms }
+    }
+}
+
+/// The parts of a server certificate worth showing on the dashboard.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CertificateSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub expires: DateTime<Utc>,
+    /// Whole days left when the check ran; negative once expired.
+    pub expires_in_days: i64,
+}
+
+impl CertificateSummary {
+    pub fn expires_soon(&self) -> bool {
+        self.expires_in_days < CERT_EXPIRY_WARNING_DAYS
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&hytale_health_checker::CertificateInfo> for CertificateSummary {
+    fn from(cert: &hytale_health_checker::CertificateInfo) -> Self {
+        Self {
+            subject: cert.subject.clone(),
+            issuer: cert.issuer.clone(),
+            expires: cert.not_after,
+            expires_in_days: cert.days_until_expiry(),
+        }
+    }
+}
+
+/// Display-ready summary of a Terraria world, as recorded by the monitor.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct WorldSummary {
+    pub name: String,
+    pub size: String,
+    /// In-game clock time as `HH:MM`.
+    pub time: String,
+    pub is_day: bool,
+    pub difficulty: String,
+    pub hardmode: Option<bool>,
+    pub events: Vec<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<WorldInfo> for WorldSummary {
+    fn from(world: WorldInfo) -> Self {
+        Self {
+            size: world.size().to_string(),
+            time: world.clock(),
+            is_day: world.is_day,
+            difficulty: world.difficulty.to_string(),
+            hardmode: world.hardmode,
+            events: world.events.iter().map(|e| e.to_string()).collect(),
+            name: world.name,
+        }
+    }
+}
+
+/// Recent checks of every service, keyed by service id, oldest first.
+///
+/// On disk it is a CSV with one row per check (`Timestamp,Service,Online,Latency`),
+/// so services can come and go without changing the columns. Files in the
+/// older fixed Terraria/Hytale layout are read as services `terraria` and
+/// `hytale`, and are rewritten in the new layout by the monitor's first write.
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct History {
-    pub terraria: Vec<ServiceStatus>,
-    pub hytale: Vec<ServiceStatus>,
+    pub services: BTreeMap<String, Vec<ServiceStatus>>,
+}
+
+/// Drops the oldest of `entries` beyond [`MAX_HISTORY_ENTRIES`], in one shift.
+fn keep_newest(entries: &mut Vec<ServiceStatus>) {
+    let excess = entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+    entries.drain(..excess);
+}
+
+impl History {
+    /// Checks of service `id`, oldest first.
+    pub fn get(&self, id: &str) -> &[ServiceStatus] {
+        self.services.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Records `entry` under its service id, dropping the oldest check once
+    /// the service has [`MAX_HISTORY_ENTRIES`].
+    pub fn push(&mut self, entry: ServiceStatus) {
+        let entries = self.services.entry(entry.service_id.clone()).or_default();
+        entries.push(entry);
+        keep_newest(entries);
+    }
+
+    /// Records `entry` without trimming, for loading many rows at once.
+    fn push_untrimmed(&mut self, entry: ServiceStatus) {
+        self.services.entry(entry.service_id.clone()).or_default().push(entry);
+    }
+
+    /// Reads a history file in either layout. Rows that don't parse are skipped.
+    pub fn from_csv(content: &str) -> anyhow::Result<Self> {
+        let mut lines = content.lines();
+        let header = lines.next().unwrap_or_default().trim();
+        let legacy = match header {
+            HISTORY_HEADER => false,
+            LEGACY_HISTORY_HEADER => true,
+            // A file we just created and haven't written to yet
+            "" => return Ok(History::default()),
+            other => anyhow::bail!("Unknown history header '{}'", other),
+        };
+
+        let mut history = History::default();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let parts: Vec<&str> = line.split(',').collect();
+            let Some(timestamp) = parts.first().and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) else {
+                continue;
+            };
+            let timestamp = timestamp.with_timezone(&Utc);
+            let entry = |id: &str, online: &str, latency: &str| ServiceStatus {
+                service_id: id.to_string(),
+                service_name: id.to_string(),
+                is_online: parse_online(online),
+                latency_ms: latency.trim().parse().unwrap_or(0),
+                timestamp,
+                ..Default::default()
+            };
+
+            if legacy {
+                if parts.len() < 5 {
+                    continue;
+                }
+                history.push_untrimmed(entry("terraria", parts[1], parts[2]));
+                history.push_untrimmed(entry("hytale", parts[3], parts[4]));
+            } else {
+                if parts.len() < 4 || parts[1].is_empty() {
+                    continue;
+                }
+                history.push_untrimmed(entry(parts[1], parts[2], parts[3]));
+            }
+        }
+        // Trimmed once at the end, as an uncompacted file holds more rows than are kept
+        history.services.values_mut().for_each(keep_newest);
+        Ok(history)
+    }
+
+    /// The history in the one-row-per-check layout, rows in time order.
+    pub fn to_csv(&self) -> String {
+        let mut entries: Vec<&ServiceStatus> = self.services.values().flatten().collect();
+        entries.sort_by_key(|entry| entry.timestamp);
+
+        let mut csv = format!("{}\n", HISTORY_HEADER);
+        for entry in entries {
+            csv.push_str(&history_row(entry));
+        }
+        csv
+    }
+}
+
+/// One check as a row of the history file.
+fn history_row(entry: &ServiceStatus) -> String {
+    let online = match entry.is_online {
+        Some(true) => "true",
+        Some(false) => "false",
+        None => "",
+    };
+    format!("{},{},{},{}\n", entry.timestamp.to_rfc3339(), entry.service_id, online, entry.latency_ms)
+}
+
+fn parse_online(value: &str) -> Option<bool> {
+    match value.trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&terraria_health_checker::AddressResult> for AddressStatus {
+    fn from(result: &terraria_health_checker::AddressResult) -> Self {
+        Self {
+            family: result.family().to_string(),
+            address: result.address.to_string(),
+            is_online: result.is_online,
+            latency_ms: result.latency_ms,
+            failure: (result.outcome != ProbeOutcome::Answered).then(|| result.outcome.to_string()),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&hytale_health_checker::AddressResult> for AddressStatus {
+    fn from(result: &hytale_health_checker::AddressResult) -> Self {
+        Self {
+            family: result.family().to_string(),
+            address: result.address.to_string(),
+            is_online: result.is_online,
+            latency_ms: result.latency_ms,
+            failure: result.error.clone().or_else(|| {
+                result.reply.as_ref().filter(|reply| reply.is_version_mismatch()).map(|reply| reply.to_string())
+            }),
+        }
+    }
+}
+
+/// The part of a service's entry every checker reports the same way.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_entry(service_name: &str, result: CheckResult, now: DateTime<Utc>) -> ServiceStatus {
+    // Can't tell whether the server or our own resolver is at fault -> No Data
+    if !result.outcome.is_conclusive() {
+        eprintln!("{} monitor error: {}", service_name, result.outcome);
+    }
+
+    ServiceStatus {
+        service_name: service_name.to_string(),
+        is_online: result.status(),
+        latency_ms: result.latency_ms,
+        timestamp: now,
+        version: result.version,
+        failure: result.failure,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn terraria_entry(service_name: &str, info: ServerInfo, now: DateTime<Utc>) -> ServiceStatus {
+    let result = CheckResult::from(&info);
+    ServiceStatus {
+        world: info.world.map(WorldSummary::from),
+        addresses: info.addresses.iter().map(AddressStatus::from).collect(),
+        ..check_entry(service_name, result, now)
+    }
 }
 
-#[derive(Clone, Debug)]
 #[cfg(not(target_arch = "wasm32"))]
-pub struct MonitorConfig {
-    pub terraria_host: String,
-    pub terraria_port: u16,
-    pub hytale_host: String,
-    pub hytale_port: u16,
-    pub history_path: PathBuf,
+fn hytale_entry_from_addresses(
+    service_name: &str,
+    results: Vec<hytale_health_checker::AddressResult>,
+    now: DateTime<Utc>,
+) -> ServiceStatus {
+    let answered = results.iter().find(|r| r.is_online);
+    ServiceStatus {
+        service_name: service_name.to_string(),
+        is_online: Some(answered.is_some()),
+        latency_ms: answered.map_or(0, |r| r.latency_ms),
+        timestamp: now,
+        loss_percent: answered.and_then(|r| r.stats.as_ref()).map(|stats| stats.loss_percent),
+        jitter_ms: answered.and_then(|r| r.stats.as_ref()).map(|stats| stats.jitter_ms),
+        retry: answered.and_then(|r| r.retry.as_ref()).map(RetrySummary::from),
+        addresses: results.iter().map(AddressStatus::from).collect(),
+        failure: match answered {
+            Some(_) => None,
+            None => results.iter().map(AddressStatus::from).find_map(|a| a.failure),
+        },
+        ..Default::default()
+    }
 }
 
+/// Entry for a Hytale check that got no reply, classified by how the probe failed.
+#[cfg(not(target_arch = "wasm32"))]
+fn hytale_failure_entry(service_name: &str, error: &anyhow::Error, now: DateTime<Utc>) -> ServiceStatus {
+    let outcome = hytale_health_checker::probe_outcome(error);
+    check_entry(service_name, CheckResult::failed(outcome, format!("{:#}", error)), now)
+}
+
+/// Attaches the latest MTU diagnostic to `entry`. While the server is down
+/// and the diagnostic found an MTU problem, that is reported as the cause.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_mtu(mut entry: ServiceStatus, mtu: Option<MtuSummary>) -> ServiceStatus {
+    if let Some(mtu) = &mtu
+        && mtu.problem
+        && entry.is_online == Some(false)
+    {
+        entry.failure = Some(format!("MTU problem: {}", mtu.detail));
+    }
+    entry.mtu = mtu;
+    entry
+}
+
+/// Adds the result of a Hytale deep probe's handshake to `entry`. A server
+/// that answers the probe but refuses the handshake counts as offline.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_hytale_handshake(
+    service_name: &str,
+    mut entry: ServiceStatus,
+    handshake: std::result::Result<hytale_health_checker::HandshakeInfo, String>,
+) -> ServiceStatus {
+    match handshake {
+        Ok(handshake) => {
+            entry.handshake_ms = Some(handshake.handshake_ms);
+            entry.certificate = handshake.certificate.as_ref().map(CertificateSummary::from);
+        }
+        Err(error) => {
+            entry.is_online = Some(false);
+            entry.failure = Some(error);
+        }
+    }
+
+    if let Some(cert) = &entry.certificate
+        && cert.expires_soon()
+    {
+        eprintln!(
+            "Warning: {} certificate for {} expires on {} ({} days left)",
+            service_name,
+            cert.subject,
+            cert.expires.format("%Y-%m-%d"),
+            cert.expires_in_days
+        );
+    }
+
+    entry
+}
+
+/// Checks every configured service on its own fixed-rate schedule, forever.
+///
+/// Each service runs in its own task, so a slow or hung server delays
+/// neither the others nor its own next check. Results go through a channel
+/// to a single writer that owns the latest and history files. Failing to
+/// write them is logged and monitoring carries on.
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn run_monitor(config: MonitorConfig) -> Result<()> {
     println!("Starting Status Monitor...");
-    println!("Monitoring Terraria at {}:{}", config.terraria_host, config.terraria_port);
-    println!("Monitoring Hytale at {}:{}", config.hytale_host, config.hytale_port);
+    for service in &config.services {
+        println!(
+            "Monitoring {} ({}) at {}:{} every {}s",
+            service.name,
+            service.checker.kind(),
+            service.host,
+            service.port,
+            service.interval_secs
+        );
+    }
     println!("History file: {:?}", config.history_path);
 
+    let (results, mut received) = mpsc::channel(config.services.len().max(1) * 4);
+    // Dropping the set aborts the service tasks
+    let mut tasks = JoinSet::new();
+    for service in &config.services {
+        tasks.spawn(run_service(ServiceState::new(service), results.clone()));
+    }
+    drop(results);
+
+    let mut recorder = Recorder::new(&config);
+    while let Some(entry) = received.recv().await {
+        if let Err(e) = recorder.record(&entry).await {
+            eprintln!("Failed to record the {} check: {:#}", entry.service_name, e);
+        }
+    }
+    Ok(())
+}
+
+/// Checks one service every interval and sends each result to the writer.
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_service(mut service: ServiceState, results: mpsc::Sender<ServiceStatus>) {
+    // Ticks stay on the grid set by the first check; a check overrunning its
+    // interval skips the ticks it missed instead of running them back to back
+    let mut ticks = tokio::time::interval(service.config.interval());
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
     loop {
+        ticks.tick().await;
         let now = Utc::now();
-        println!("Running checks at {}", now);
-
-        // Check Terraria (blocking)
-        let t_host = config.terraria_host.clone();
-        let t_port = config.terraria_port;
-        let terraria_result = tokio::task::spawn_blocking(move || {
-            terraria_health_checker::check_server_status(&t_host, t_port)
-        }).await;
-
-        let terraria_entry = match terraria_result {
-            Ok(Ok(info)) => ServiceStatus {
-                service_name: "Terraria".to_string(),
-                is_online: Some(info.is_online),
-                latency_ms: info.latency_ms,
-                timestamp: now,
-            },
-            Ok(Err(e)) => {
-                eprintln!("Terraria monitor error: {:?}", e);
-                 ServiceStatus {
-                    service_name: "Terraria".to_string(),
-                    is_online: None, // DNS or other error -> No Data
-                    latency_ms: 0,
-                    timestamp: now,
-                }
-            },
-            Err(e) => {
-                eprintln!("Terraria task error: {:?}", e);
-                ServiceStatus {
-                    service_name: "Terraria".to_string(),
-                    is_online: None,
-                    latency_ms: 0,
-                    timestamp: now,
-                }
-            },
+        println!("Checking {} at {}", service.config.name, now);
+        let entry = service.check(now).await;
+        if results.send(entry).await.is_err() {
+            return;
+        }
+        service.follow_up(now).await;
+    }
+}
+
+/// The single writer of the latest and history files.
+#[cfg(not(target_arch = "wasm32"))]
+struct Recorder {
+    latest_path: PathBuf,
+    history: HistoryLog,
+    #[cfg(feature = "sqlite")]
+    sqlite: Option<sqlite::SqliteSink>,
+    /// Latest entry of each service, in config order
+    latest: Vec<(String, Option<ServiceStatus>)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Recorder {
+    fn new(config: &MonitorConfig) -> Self {
+        Self {
+            latest_path: config.latest_path(),
+            history: HistoryLog::new(&config.history_path),
+            #[cfg(feature = "sqlite")]
+            sqlite: config.sqlite.clone().map(sqlite::SqliteSink::new),
+            latest: config.services.iter().map(|service| (service.id.clone(), None)).collect(),
+        }
+    }
+
+    async fn record(&mut self, entry: &ServiceStatus) -> Result<()> {
+        if let Some((_, latest)) = self.latest.iter_mut().find(|(id, _)| *id == entry.service_id) {
+            *latest = Some(entry.clone());
+        }
+        let entries = std::slice::from_ref(entry);
+
+        // Latest goes first so it is fresh by the time history watchers react.
+        // Each store is written even when another could not be
+        let latest: Vec<ServiceStatus> = self.latest.iter().filter_map(|(_, latest)| latest.clone()).collect();
+        #[cfg_attr(not(feature = "sqlite"), allow(unused_mut))]
+        let mut saved = vec![
+            save_latest(&self.latest_path, &latest)
+                .await
+                .with_context(|| format!("Failed to write {}", self.latest_path.display())),
+            self.history.append(entries).await.context("Failed to append to the history"),
+        ];
+
+        #[cfg(feature = "sqlite")]
+        if let Some(sqlite) = &mut self.sqlite {
+            saved.push(sqlite.record(entries).await.context("Failed to write the SQLite history"));
+        }
+
+        let failures: Vec<String> = saved.into_iter().filter_map(Result::err).map(|e| format!("{:#}", e)).collect();
+        if !failures.is_empty() {
+            anyhow::bail!("{}", failures.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// One configured service and the check that carries its state from one check to the next.
+#[cfg(not(target_arch = "wasm32"))]
+struct ServiceState {
+    config: ServiceConfig,
+    checker: Box<dyn ServiceCheck>,
+}
+
+/// A [`HealthCheck`] as the monitor runs it. The hooks add what a game
+/// reports beyond the common [`CheckResult`] and keep state between checks;
+/// a checker with nothing to add plugs in with an empty impl.
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+trait ServiceCheck: HealthCheck {
+    /// Runs before each check, outside its timeout.
+    async fn prepare(&mut self, _service: &ServiceConfig) {}
+
+    /// Checks the server once. Without more to report, the entry holds what
+    /// [`HealthCheck::check`] found.
+    async fn check_service(&mut self, service: &ServiceConfig, now: DateTime<Utc>) -> ServiceStatus {
+        check_entry(&service.name, self.check().await, now)
+    }
+
+    /// Adds what earlier checks found to an entry, including that of a check
+    /// that timed out.
+    fn annotate(&mut self, entry: ServiceStatus) -> ServiceStatus {
+        entry
+    }
+
+    /// Work a check called for that must not hold up its result, run once
+    /// the result is sent.
+    async fn follow_up(&mut self, _service: &ServiceConfig, _now: DateTime<Utc>) {}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ServiceState {
+    fn new(config: &ServiceConfig) -> Self {
+        let checker: Box<dyn ServiceCheck> = match config.checker {
+            CheckerConfig::Terraria { deep_probe } => Box::new(TerrariaService::new(config, deep_probe)),
+            CheckerConfig::Hytale { deep_probe, .. } => Box::new(HytaleService::new(config, deep_probe)),
         };
+        Self { config: config.clone(), checker }
+    }
 
-        // Check Hytale (async)
-        let hytale_result = hytale_health_checker::check_hytale_status(&config.hytale_host, config.hytale_port).await;
-        let hytale_entry = match hytale_result {
-            Ok(info) => ServiceStatus {
-                service_name: "Hytale".to_string(),
-                is_online: Some(info.is_online),
-                latency_ms: info.latency_ms,
-                timestamp: now,
-            },
-            Err(e) => {
-                let err_str = e.to_string();
-                let is_timeout = err_str.contains("Timeout");
-                if is_timeout {
-                     ServiceStatus {
-                        service_name: "Hytale".to_string(),
-                        is_online: Some(false),
-                        latency_ms: 0,
-                        timestamp: now,
-                    }
-                } else {
-                    eprintln!("Hytale monitor error: {:?}", e);
-                    ServiceStatus {
-                        service_name: "Hytale".to_string(),
-                        is_online: None, // No Data
-                        latency_ms: 0,
-                        timestamp: now,
-                    }
-                }
-            },
+    async fn check(&mut self, now: DateTime<Utc>) -> ServiceStatus {
+        let service = &self.config;
+        self.checker.prepare(service).await;
+        let check = self.checker.check_service(service, now);
+        let entry = match tokio::time::timeout(service.timeout() + TIMEOUT_GRACE, check).await {
+            Ok(entry) => entry,
+            Err(_) => timed_out_entry(service, now),
         };
+        let entry = self.checker.annotate(entry);
+        ServiceStatus { service_id: service.id.clone(), service_name: service.name.clone(), ..entry }
+    }
+
+    async fn follow_up(&mut self, now: DateTime<Utc>) {
+        self.checker.follow_up(&self.config, now).await;
+    }
+}
 
-        // Update History
-        update_history(&config.history_path, terraria_entry, hytale_entry).await?;
+/// Entry for a check that did not finish within the service's timeout.
+#[cfg(not(target_arch = "wasm32"))]
+fn timed_out_entry(service: &ServiceConfig, now: DateTime<Utc>) -> ServiceStatus {
+    let failure = format!("No result within {}s", service.timeout().as_secs());
+    check_entry(&service.name, CheckResult::failed(ProbeOutcome::Timeout, failure), now)
+}
+
+/// When to look for the release a Terraria server accepts. Discovery may
+/// take dozens of handshakes, so each one that finds nothing doubles the wait
+/// before the next, from [`DISCOVERY_RETRY`] up to [`DISCOVERY_RETRY_MAX`].
+#[cfg(not(target_arch = "wasm32"))]
+struct VersionDiscovery {
+    /// Next discovery; `None` while the announced release is accepted
+    due: Option<Instant>,
+    /// Discoveries in a row that found no release
+    failures: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VersionDiscovery {
+    fn due_now() -> Self {
+        Self { due: Some(Instant::now()), failures: 0 }
+    }
+
+    fn is_due(&self) -> bool {
+        self.due.is_some_and(|due| due <= Instant::now())
+    }
+
+    fn found(&mut self) {
+        *self = Self { due: None, failures: 0 };
+    }
 
-        tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+    /// Schedules the next discovery after one that found nothing; returns the wait.
+    fn failed(&mut self) -> std::time::Duration {
+        let wait = DISCOVERY_RETRY.saturating_mul(1 << self.failures.min(16)).min(DISCOVERY_RETRY_MAX);
+        self.failures += 1;
+        self.due = Some(Instant::now() + wait);
+        wait
+    }
+
+    /// The server rejected the announced release. Looks again right away,
+    /// unless discovery is already backing off.
+    fn rejected(&mut self) {
+        if self.due.is_none() {
+            *self = Self::due_now();
+        }
     }
 }
 
+/// Terraria check of a service, announcing the release the last discovery found.
 #[cfg(not(target_arch = "wasm32"))]
-async fn update_history(path: &Path, terraria: ServiceStatus, hytale: ServiceStatus) -> Result<()> {
-    let mut history = load_history(path).await.unwrap_or_default();
+struct TerrariaService {
+    check: TerrariaCheck,
+    discovery: VersionDiscovery,
+}
 
-    history.terraria.push(terraria);
-    if history.terraria.len() > MAX_HISTORY_ENTRIES {
-        history.terraria.remove(0);
+#[cfg(not(target_arch = "wasm32"))]
+impl TerrariaService {
+    fn new(service: &ServiceConfig, deep: bool) -> Self {
+        let options = CheckOptions {
+            // Leaves time to try a second address when the first doesn't connect
+            connect_timeout: service.timeout() / 2,
+            deadline: service.timeout(),
+            deep,
+            probe_all_addresses: service.probe_all_addresses,
+            ..Default::default()
+        };
+        Self { check: TerrariaCheck::new(&service.host, service.port, options), discovery: VersionDiscovery::due_now() }
     }
+}
 
-    history.hytale.push(hytale);
-    if history.hytale.len() > MAX_HISTORY_ENTRIES {
-        history.hytale.remove(0);
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl HealthCheck for TerrariaService {
+    fn name(&self) -> &str {
+        self.check.name()
     }
 
-    save_history(path, &history).await?;
-    Ok(())
+    async fn check(&self) -> CheckResult {
+        self.check.check().await
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-async fn load_history(path: &Path) -> Result<History> {
-    if path.exists() {
-        let content = fs::read_to_string(path).await?;
-        let mut history = History::default();
+#[async_trait]
+impl ServiceCheck for TerrariaService {
+    // Not part of the timed check: each handshake of the discovery has its own
+    // deadline, and a long discovery must not make a healthy server look offline
+    async fn prepare(&mut self, service: &ServiceConfig) {
+        if !self.discovery.is_due() {
+            return;
+        }
+        let TerrariaCheck { host, port, options } = &mut self.check;
+        let discovered = terraria_health_checker::discover_terraria_version(host, *port, options).await;
+        match discovered.ok().flatten() {
+            Some(version @ ServerVersion { release: Some(found), .. }) => {
+                println!("{} server version: {}", service.name, version);
+                options.release = found;
+                self.discovery.found();
+            }
+            _ => {
+                let wait = self.discovery.failed();
+                eprintln!("{} server version not found, next try in {}s", service.name, wait.as_secs());
+            }
+        }
+    }
 
-        for (i, line) in content.lines().enumerate() {
-            if i == 0 { continue; } // Skip header
-            if line.trim().is_empty() { continue; }
+    async fn check_service(&mut self, service: &ServiceConfig, now: DateTime<Utc>) -> ServiceStatus {
+        let TerrariaCheck { host, port, options } = &self.check;
+        let result = terraria_health_checker::check_terraria_status(host, *port, options).await;
 
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 5 { continue; }
+        if result.handshake == Some(HandshakeOutcome::Rejected(DisconnectReason::VersionMismatch)) {
+            self.discovery.rejected();
+        }
+        terraria_entry(&service.name, result, now)
+    }
+}
 
-            let timestamp = DateTime::parse_from_rfc3339(parts[0])?.with_timezone(&Utc);
+/// Probe settings of a Hytale service, waiting for replies as long as its timeout.
+#[cfg(not(target_arch = "wasm32"))]
+fn hytale_probe(service: &ServiceConfig) -> ProbeOptions {
+    ProbeOptions { timeout: Some(service.timeout()), ..service.checker.hytale_probe().unwrap_or_default() }
+}
 
-            let t_online: Option<bool> = match parts[1].trim() {
-                "true" => Some(true),
-                "false" => Some(false),
-                _ => None,
-            };
-            let t_latency: u128 = parts[2].parse().unwrap_or(0);
+/// Hytale check of a service, and the MTU diagnostic it runs now and then.
+#[cfg(not(target_arch = "wasm32"))]
+struct HytaleService {
+    check: HytaleCheck,
+    deep: bool,
+    probe_all_addresses: bool,
+    /// Latest MTU diagnostic, and checks since it ran
+    mtu: Option<MtuSummary>,
+    checks_since_mtu: u32,
+    /// The diagnostic runs once the check that called for it is recorded
+    mtu_due: bool,
+    was_offline: bool,
+}
 
-            let h_online: Option<bool> = match parts[3].trim() {
-                "true" => Some(true),
-                "false" => Some(false),
-                _ => None,
+#[cfg(not(target_arch = "wasm32"))]
+impl HytaleService {
+    fn new(service: &ServiceConfig, deep: bool) -> Self {
+        Self {
+            check: HytaleCheck::new(&service.host, service.port, hytale_probe(service)),
+            deep,
+            probe_all_addresses: service.probe_all_addresses,
+            mtu: None,
+            checks_since_mtu: MTU_CHECK_EVERY,
+            mtu_due: false,
+            was_offline: false,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl HealthCheck for HytaleService {
+    fn name(&self) -> &str {
+        self.check.name()
+    }
+
+    async fn check(&self) -> CheckResult {
+        self.check.check().await
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ServiceCheck for HytaleService {
+    // One probe per check: the deep handshake goes to the address that answered it
+    async fn check_service(&mut self, service: &ServiceConfig, now: DateTime<Utc>) -> ServiceStatus {
+        let HytaleCheck { host, port, options: probe } = &self.check;
+        if self.probe_all_addresses {
+            let results = match hytale_health_checker::check_hytale_addresses(host, *port, probe).await {
+                Ok(results) => results,
+                Err(e) => return hytale_failure_entry(&service.name, &e, now),
+            };
+            let answered = results.iter().find(|result| result.is_online).map(|result| result.address);
+            let entry = hytale_entry_from_addresses(&service.name, results, now);
+            match answered {
+                Some(addr) if self.deep => {
+                    let handshake = hytale_health_checker::check_hytale_handshake(host, addr, probe).await;
+                    with_hytale_handshake(&service.name, entry, handshake.map_err(|e| format!("{:#}", e)))
+                }
+                _ => entry,
+            }
+        } else {
+            let checked = if self.deep {
+                hytale_health_checker::check_hytale_deep(host, *port, probe).await
+            } else {
+                hytale_health_checker::check_hytale_status_with_options(host, *port, probe).await
             };
-            let h_latency: u128 = parts[4].parse().unwrap_or(0);
+            let mut info = match checked {
+                Ok(info) => info,
+                Err(e) => return hytale_failure_entry(&service.name, &e, now),
+            };
+            let entry = ServiceStatus {
+                loss_percent: Some(info.stats.loss_percent),
+                jitter_ms: Some(info.stats.jitter_ms),
+                retry: info.retry.as_ref().map(RetrySummary::from),
+                ..check_entry(&service.name, CheckResult::from(&info), now)
+            };
+            match (info.handshake.take(), info.handshake_error.take()) {
+                (Some(handshake), _) => with_hytale_handshake(&service.name, entry, Ok(handshake)),
+                (None, Some(error)) => with_hytale_handshake(&service.name, entry, Err(error)),
+                (None, None) => entry,
+            }
+        }
+    }
 
-            history.terraria.push(ServiceStatus {
-                service_name: "Terraria".to_string(),
-                is_online: t_online,
-                latency_ms: t_latency,
-                timestamp,
-            });
+    fn annotate(&mut self, entry: ServiceStatus) -> ServiceStatus {
+        // A new outage gets a fresh diagnostic, so a path dropping large datagrams is named as such
+        let offline = entry.is_online == Some(false);
+        let went_offline = offline && !self.was_offline;
+        self.was_offline = offline;
+        self.checks_since_mtu += 1;
+        if self.checks_since_mtu >= MTU_CHECK_EVERY || went_offline {
+            self.checks_since_mtu = 0;
+            self.mtu_due = true;
+        }
+        with_mtu(entry, self.mtu.clone())
+    }
 
-            history.hytale.push(ServiceStatus {
-                service_name: "Hytale".to_string(),
-                is_online: h_online,
-                latency_ms: h_latency,
-                timestamp,
-            });
+    /// Runs the MTU diagnostic a check called for. Its result goes with the following checks.
+    async fn follow_up(&mut self, service: &ServiceConfig, now: DateTime<Utc>) {
+        if !std::mem::take(&mut self.mtu_due) {
+            return;
+        }
+
+        let HytaleCheck { host, port, options: probe } = &self.check;
+        // The search ends on a size that times out, so it takes several probe
+        // timeouts; bounded by the interval, it delays the next check by one tick at most
+        let diagnostic = hytale_health_checker::check_hytale_mtu(host, *port, probe);
+        match tokio::time::timeout(service.interval(), diagnostic).await {
+            Ok(Ok(report)) => {
+                if report.is_problem() {
+                    eprintln!("Warning: {} path MTU problem at {}: {}", service.name, report.address, report);
+                }
+                self.mtu = Some(MtuSummary::new(&report, now));
+            }
+            Ok(Err(e)) => eprintln!("{} MTU diagnostic error: {:?}", service.name, e),
+            Err(_) => eprintln!("{} MTU diagnostic did not finish within {}s", service.name, service.interval_secs),
         }
-        return Ok(history);
     }
-    Ok(History::default())
 }
 
+/// Writer of the history file. Each check is appended as one row; once a
+/// service has [`HISTORY_COMPACT_SLACK`] rows beyond [`MAX_HISTORY_ENTRIES`],
+/// the file is rewritten with only the retained rows.
+///
+/// Rewrites go to a temporary file that is then renamed over the history, so
+/// readers always see a complete file.
 #[cfg(not(target_arch = "wasm32"))]
-async fn save_history(path: &Path, history: &History) -> Result<()> {
-    // Ensure directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).await?;
+pub struct HistoryLog {
+    path: PathBuf,
+    /// Rows of each service in the file; `None` until the file is first compacted
+    rows: Option<BTreeMap<String, usize>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HistoryLog {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), rows: None }
     }
-    let mut file = File::create(path).await?;
-    file.write_all(b"Timestamp,TerrariaOnline,TerrariaLatency,HytaleOnline,HytaleLatency\n").await?;
 
-    let len = std::cmp::min(history.terraria.len(), history.hytale.len());
-    for i in 0..len {
-        let t = &history.terraria[i];
-        let h = &history.hytale[i];
+    /// Appends one row per entry. The first call compacts the file, which
+    /// also rewrites files in the legacy layout.
+    pub async fn append(&mut self, entries: &[ServiceStatus]) -> Result<()> {
+        // Someone may have removed the file, which would lose the header
+        if self.rows.is_none() || !self.path.exists() {
+            self.compact().await?;
+        }
 
-        let t_online_str = match t.is_online {
-            Some(true) => "true",
-            Some(false) => "false",
-            None => "",
-        };
-        let h_online_str = match h.is_online {
-            Some(true) => "true",
-            Some(false) => "false",
-            None => "",
+        let rows: String = entries.iter().map(history_row).collect();
+        let mut file = fs::OpenOptions::new().append(true).open(&self.path).await?;
+        file.write_all(rows.as_bytes()).await?;
+        // tokio finishes writes in the background; the file must be complete once we return
+        file.flush().await?;
+
+        let counts = self.rows.get_or_insert_default();
+        for entry in entries {
+            *counts.entry(entry.service_id.clone()).or_default() += 1;
+        }
+        if counts.values().any(|&rows| rows > MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK) {
+            self.compact().await?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the file with the last [`MAX_HISTORY_ENTRIES`] rows of each service.
+    ///
+    /// A file that can't be read (unknown header, not UTF-8) is renamed aside
+    /// with an `.unreadable-<time>` suffix and the history starts over, so a
+    /// damaged file never stops the monitor.
+    pub async fn compact(&mut self) -> Result<()> {
+        let history = match load_history(&self.path).await {
+            Ok(history) => history,
+            Err(e) => {
+                let aside = sibling_path(&self.path, &format!(".unreadable-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+                fs::rename(&self.path, &aside).await?;
+                eprintln!("{:#}; moved it to {} and starting a new history", e, aside.display());
+                History::default()
+            }
         };
+        write_atomically(&self.path, history.to_csv().as_bytes(), true).await?;
 
-        let line = format!("{},{},{},{},{}\n",
-            t.timestamp.to_rfc3339(),
-            t_online_str,
-            t.latency_ms,
-            h_online_str,
-            h.latency_ms
-        );
-        file.write_all(line.as_bytes()).await?;
+        self.rows = Some(history.services.iter().map(|(id, entries)| (id.clone(), entries.len())).collect());
+        Ok(())
     }
+}
+
+/// `path` with `suffix` appended to its file name.
+#[cfg(not(target_arch = "wasm32"))]
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replaces the file at `path` with `content` by writing a temporary file
+/// and renaming it into place, so readers never see a partial file.
+///
+/// With `durable`, the content reaches the disk before the rename, so a power
+/// cut can't leave an empty file in place of the old one. That costs a flush
+/// per write, which files rewritten every check go without.
+#[cfg(not(target_arch = "wasm32"))]
+async fn write_atomically(path: &Path, content: &[u8], durable: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let temp_path = sibling_path(path, ".tmp");
+    let mut file = File::create(&temp_path).await?;
+    file.write_all(content).await?;
+    if durable {
+        file.sync_all().await?;
+    } else {
+        // tokio finishes writes in the background; the file must be complete before the rename
+        file.flush().await?;
+    }
+    fs::rename(&temp_path, path).await?;
     Ok(())
 }
+
+/// Reads the history file, in either layout; empty when there is none yet.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn load_history(path: &Path) -> Result<History> {
+    if !path.exists() {
+        return Ok(History::default());
+    }
+    let content = fs::read_to_string(path).await?;
+    History::from_csv(&content).with_context(|| format!("Failed to read history {}", path.display()))
+}
+
+/// Reads the latest full check results written by the monitor.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn load_latest(path: &Path) -> Result<Vec<ServiceStatus>> {
+    let content = fs::read_to_string(path).await?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Rewrites the latest-status file. It is replaced after every check, and
+/// the next check restores it anyway, so it is not flushed to disk.
+#[cfg(not(target_arch = "wasm32"))]
+async fn save_latest(path: &Path, latest: &[ServiceStatus]) -> Result<()> {
+    write_atomically(path, serde_json::to_string_pretty(latest)?.as_bytes(), false).await
+}
@@ -0,0 +1,238 @@
+//! Check history in a SQLite database, for range queries, per-service uptime
+//! windows and retention well beyond what the CSV history keeps. Enabled by
+//! the `sqlite` feature and the `[sqlite]` table of the config.
+//!
+//! Every check is a row of the `checks` table, indexed by service and time.
+//! Timestamps are stored as Unix milliseconds.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Row};
+use tokio::time::Instant;
+
+use crate::{History, ServiceStatus, SqliteConfig};
+
+/// Prune expired checks at most this often.
+const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+/// How long a write waits for another process (a backup, a query from the
+/// shell) to release the database before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS checks (
+        id INTEGER PRIMARY KEY,
+        service_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        is_online INTEGER,
+        latency_ms INTEGER NOT NULL,
+        failure TEXT
+    );
+    CREATE INDEX IF NOT EXISTS checks_service_time ON checks (service_id, timestamp);
+";
+
+/// Checks and uptime of one service over a time window.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Uptime {
+    pub checks: u64,
+    pub online: u64,
+    /// Checks that said nothing about the server (e.g. a failed DNS lookup).
+    pub no_data: u64,
+    /// Average latency of the online checks.
+    pub avg_latency_ms: Option<f64>,
+}
+
+impl Uptime {
+    /// Share of the conclusive checks that found the server online, in percent.
+    pub fn percent(&self) -> Option<f64> {
+        let conclusive = self.checks - self.no_data;
+        (conclusive > 0).then(|| self.online as f64 / conclusive as f64 * 100.0)
+    }
+}
+
+/// A SQLite history database. Queries run on tokio's blocking threads.
+#[derive(Clone)]
+pub struct SqliteHistory {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteHistory {
+    /// Opens the database at `path`, creating it and the `checks` table if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open SQLite history {}", path.display()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    /// Records `entries`, in one transaction.
+    pub async fn insert(&self, entries: &[ServiceStatus]) -> Result<()> {
+        let entries = entries.to_vec();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut insert = transaction.prepare_cached(
+                    "INSERT INTO checks (service_id, timestamp, is_online, latency_ms, failure) VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+                for entry in &entries {
+                    insert.execute(params![
+                        entry.service_id,
+                        entry.timestamp.timestamp_millis(),
+                        entry.is_online,
+                        entry.latency_ms as i64,
+                        entry.failure,
+                    ])?;
+                }
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    /// Deletes the checks older than `cutoff`. Returns how many were deleted.
+    pub async fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        self.run(move |connection| {
+            connection.execute("DELETE FROM checks WHERE timestamp < ?1", [cutoff.timestamp_millis()])
+        })
+        .await
+    }
+
+    /// Checks of service `id` from `from` (inclusive) to `to` (exclusive), oldest first.
+    pub async fn range(&self, id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<ServiceStatus>> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            let mut query = connection.prepare_cached(
+                "SELECT service_id, timestamp, is_online, latency_ms, failure FROM checks
+                 WHERE service_id = ?1 AND timestamp >= ?2 AND timestamp < ?3 ORDER BY timestamp",
+            )?;
+            let rows = query.query_map(params![id, from.timestamp_millis(), to.timestamp_millis()], check_from_row)?;
+            rows.collect()
+        })
+        .await
+    }
+
+    /// Uptime of service `id` from `from` (inclusive) to `to` (exclusive).
+    pub async fn uptime(&self, id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Uptime> {
+        Ok(self.uptime_by_service(from, to).await?.remove(id).unwrap_or_default())
+    }
+
+    /// Uptime of every service with checks from `from` (inclusive) to `to` (exclusive).
+    pub async fn uptime_by_service(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<BTreeMap<String, Uptime>> {
+        self.run(move |connection| {
+            let mut query = connection.prepare_cached(
+                "SELECT service_id, COUNT(*), COUNT(CASE WHEN is_online = 1 THEN 1 END),
+                        COUNT(CASE WHEN is_online IS NULL THEN 1 END),
+                        AVG(CASE WHEN is_online = 1 THEN latency_ms END)
+                 FROM checks WHERE timestamp >= ?1 AND timestamp < ?2 GROUP BY service_id",
+            )?;
+            let rows = query.query_map(params![from.timestamp_millis(), to.timestamp_millis()], |row| {
+                let uptime = Uptime {
+                    checks: row.get::<_, i64>(1)? as u64,
+                    online: row.get::<_, i64>(2)? as u64,
+                    no_data: row.get::<_, i64>(3)? as u64,
+                    avg_latency_ms: row.get(4)?,
+                };
+                Ok((row.get(0)?, uptime))
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    /// The last `per_service` checks of every service, the way the CSV
+    /// history presents them.
+    pub async fn history(&self, per_service: usize) -> Result<History> {
+        self.run(move |connection| {
+            let mut query = connection.prepare_cached(
+                "SELECT service_id, timestamp, is_online, latency_ms, failure FROM (
+                     SELECT *, ROW_NUMBER() OVER (PARTITION BY service_id ORDER BY timestamp DESC) AS age FROM checks
+                 ) WHERE age <= ?1 ORDER BY timestamp",
+            )?;
+            let mut history = History::default();
+            for entry in query.query_map([per_service as i64], check_from_row)? {
+                let entry = entry?;
+                history.services.entry(entry.service_id.clone()).or_default().push(entry);
+            }
+            Ok(history)
+        })
+        .await
+    }
+
+    async fn run<T, F>(&self, query: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            query(&mut connection)
+        })
+        .await?;
+        Ok(result?)
+    }
+}
+
+/// A check read back from the database. Only what the table keeps is filled in.
+fn check_from_row(row: &Row) -> rusqlite::Result<ServiceStatus> {
+    let service_id: String = row.get(0)?;
+    let timestamp = DateTime::from_timestamp_millis(row.get(1)?).unwrap_or_default();
+    Ok(ServiceStatus {
+        service_name: service_id.clone(),
+        service_id,
+        timestamp,
+        is_online: row.get(2)?,
+        latency_ms: row.get::<_, i64>(3)? as u128,
+        failure: row.get(4)?,
+        ..Default::default()
+    })
+}
+
+/// The monitor's writer into the SQLite history: opens the database on the
+/// first write and prunes expired checks about once an hour. After a failed
+/// write the database is reopened by the next one.
+pub(crate) struct SqliteSink {
+    config: SqliteConfig,
+    history: Option<SqliteHistory>,
+    last_prune: Option<Instant>,
+}
+
+impl SqliteSink {
+    pub(crate) fn new(config: SqliteConfig) -> Self {
+        Self { config, history: None, last_prune: None }
+    }
+
+    pub(crate) async fn record(&mut self, entries: &[ServiceStatus]) -> Result<()> {
+        let recorded = self.write(entries).await;
+        if recorded.is_err() {
+            self.history = None;
+        }
+        recorded
+    }
+
+    async fn write(&mut self, entries: &[ServiceStatus]) -> Result<()> {
+        let history = match &self.history {
+            Some(history) => history,
+            None => self.history.insert(SqliteHistory::open(&self.config.path)?),
+        };
+        history.insert(entries).await?;
+
+        if self.last_prune.is_none_or(|pruned| pruned.elapsed() >= PRUNE_EVERY) {
+            let cutoff = Utc::now() - chrono::Duration::days(self.config.retention_days as i64);
+            let pruned = history.prune(cutoff).await?;
+            if pruned > 0 {
+                println!("Pruned {} checks older than {} from the SQLite history", pruned, cutoff);
+            }
+            self.last_prune = Some(Instant::now());
+        }
+        Ok(())
+    }
+}
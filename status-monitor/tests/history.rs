@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use chrono::{Duration, TimeZone, Utc};
+use status_monitor::{
+    load_history, History, HistoryLog, ServiceStatus, HISTORY_COMPACT_SLACK, HISTORY_HEADER, MAX_HISTORY_ENTRIES,
+};
+
+/// A fresh history file path for one test.
+fn history_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("status-history-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("status-history.csv")
+}
+
+/// `count` checks of service `id`, a minute apart.
+fn checks(id: &str, count: usize) -> Vec<ServiceStatus> {
+    let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    (0..count)
+        .map(|i| ServiceStatus {
+            service_id: id.to_string(),
+            service_name: id.to_string(),
+            is_online: Some(i % 2 == 0),
+            latency_ms: i as u128,
+            timestamp: start + Duration::minutes(i as i64),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn line_count(path: &PathBuf) -> usize {
+    std::fs::read_to_string(path).unwrap().lines().count()
+}
+
+#[tokio::test]
+async fn checks_are_appended() {
+    let path = history_path("append");
+    let mut log = HistoryLog::new(&path);
+    let entries = checks("terraria", 3);
+
+    log.append(&entries[..2]).await.unwrap();
+    let before = std::fs::read_to_string(&path).unwrap();
+    log.append(&entries[2..]).await.unwrap();
+    let after = std::fs::read_to_string(&path).unwrap();
+
+    assert!(before.starts_with(HISTORY_HEADER));
+    assert!(after.starts_with(&before), "earlier rows were rewritten");
+    assert_eq!(after.lines().count(), 4);
+    assert_eq!(load_history(&path).await.unwrap().get("terraria"), &entries[..]);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn pushing_past_the_limit_drops_the_oldest() {
+    let entries = checks("terraria", MAX_HISTORY_ENTRIES + 2);
+    let mut history = History::default();
+    for entry in entries.iter().cloned() {
+        history.push(entry);
+    }
+    assert_eq!(history.get("terraria"), &entries[2..]);
+}
+
+#[tokio::test]
+async fn log_is_compacted_past_the_slack() {
+    let path = history_path("compact");
+    let mut log = HistoryLog::new(&path);
+    let entries = checks("hytale", MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK + 1);
+
+    log.append(&entries[..MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK]).await.unwrap();
+    let within_slack = line_count(&path);
+    let read = load_history(&path).await.unwrap();
+    log.append(&entries[MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK..]).await.unwrap();
+
+    assert_eq!(within_slack, 1 + MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK);
+    // Readers only ever see the retained checks
+    assert_eq!(read.get("hytale"), &entries[HISTORY_COMPACT_SLACK..MAX_HISTORY_ENTRIES + HISTORY_COMPACT_SLACK]);
+    assert_eq!(line_count(&path), 1 + MAX_HISTORY_ENTRIES);
+    assert_eq!(load_history(&path).await.unwrap().get("hytale"), &entries[HISTORY_COMPACT_SLACK + 1..]);
+    let files: Vec<_> = std::fs::read_dir(path.parent().unwrap()).unwrap().collect();
+    assert_eq!(files.len(), 1, "temporary file left behind");
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn removed_file_gets_its_header_back() {
+    let path = history_path("removed");
+    let mut log = HistoryLog::new(&path);
+    let entries = checks("terraria", 2);
+
+    log.append(&entries[..1]).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    log.append(&entries[1..]).await.unwrap();
+
+    assert_eq!(load_history(&path).await.unwrap().get("terraria"), &entries[1..]);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn unreadable_file_is_set_aside() {
+    let path = history_path("unreadable");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "Not,A,History\n1,2,3\n").unwrap();
+    let mut log = HistoryLog::new(&path);
+    let entries = checks("terraria", 1);
+
+    log.append(&entries).await.unwrap();
+
+    assert_eq!(load_history(&path).await.unwrap().get("terraria"), &entries[..]);
+    let aside: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|file| file.to_string_lossy().contains(".unreadable-"))
+        .collect();
+    assert_eq!(aside.len(), 1);
+    assert_eq!(std::fs::read_to_string(&aside[0]).unwrap(), "Not,A,History\n1,2,3\n");
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,438 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use hytale_health_checker::mock as hytale;
+use hytale_health_checker::QUIC_V2;
+use status_monitor::{
+    load_history, load_latest, run_monitor, CheckerConfig, MonitorConfig, ServiceConfig, ServiceStatus, HISTORY_HEADER,
+    LEGACY_HISTORY_HEADER,
+};
+use tokio::task::JoinHandle;
+use terraria_health_checker::mock as terraria;
+use terraria_health_checker::{Difficulty, WorldInfo};
+
+/// A fresh directory for one test's history and latest files.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("status-monitor-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn config(terraria: &terraria::MockServer, hytale: &hytale::MockServer, dir: &Path) -> MonitorConfig {
+    MonitorConfig {
+        history_path: dir.join("status-history.csv"),
+        sqlite: None,
+        services: vec![
+            ServiceConfig::new("terraria", "Terraria", "127.0.0.1", terraria.port(), terraria_checker(false)),
+            ServiceConfig::new("hytale", "Hytale", "127.0.0.1", hytale.port(), hytale_checker()),
+        ],
+    }
+}
+
+fn terraria_checker(deep_probe: bool) -> CheckerConfig {
+    CheckerConfig::Terraria { deep_probe }
+}
+
+fn hytale_checker() -> CheckerConfig {
+    CheckerConfig::Hytale {
+        strategy: Default::default(),
+        sni: None,
+        alpn: Vec::new(),
+        burst: 1,
+        deep_probe: false,
+    }
+}
+
+/// The history rows the dashboard reads, without the header.
+fn history_rows(config: &MonitorConfig) -> Vec<Vec<String>> {
+    let csv = std::fs::read_to_string(&config.history_path).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(HISTORY_HEADER));
+    lines.map(|line| line.split(',').map(str::to_string).collect()).collect()
+}
+
+/// Online column of the history rows of service `id`.
+fn history_online(config: &MonitorConfig, id: &str) -> Vec<String> {
+    history_rows(config).into_iter().filter(|row| row[1] == id).map(|row| row[2].clone()).collect()
+}
+
+/// Whether the history is in the current layout with `count` checks of service `id`.
+fn has_checks(config: &MonitorConfig, id: &str, count: usize) -> bool {
+    // Rows are only counted once complete, as the writer may be mid-append
+    std::fs::read_to_string(&config.history_path).is_ok_and(|csv| {
+        csv.starts_with(HISTORY_HEADER)
+            && csv.lines().filter(|line| line.split(',').nth(1) == Some(id) && line.split(',').count() == 4).count()
+                >= count
+    })
+}
+
+/// Waits for the history to hold `count` checks of service `id`. Yielding in a
+/// loop keeps the paused clock from moving on by itself while the checks talk
+/// to the mock servers.
+async fn wait_for_checks(config: &MonitorConfig, id: &str, count: usize) {
+    let give_up = std::time::Instant::now() + Duration::from_secs(10);
+    while !has_checks(config, id, count) {
+        assert!(std::time::Instant::now() < give_up, "{} was not checked {} times", id, count);
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Gives network IO in flight, such as a diagnostic that follows a check,
+/// time to finish before the test moves the paused clock.
+async fn settle() {
+    let until = std::time::Instant::now() + Duration::from_millis(50);
+    while std::time::Instant::now() < until {
+        tokio::task::yield_now().await;
+    }
+}
+
+/// [`run_monitor`] on the test's paused clock, driven a round of checks at a time.
+struct Running {
+    config: MonitorConfig,
+    rounds: usize,
+    task: JoinHandle<anyhow::Result<()>>,
+}
+
+impl Running {
+    fn start(config: &MonitorConfig) -> Self {
+        Self { config: config.clone(), rounds: 0, task: tokio::spawn(run_monitor(config.clone())) }
+    }
+
+    /// Waits for every service's next check and returns the latest entries,
+    /// in config order. Later rounds first move the clock on by an interval.
+    async fn round(&mut self) -> Vec<ServiceStatus> {
+        if self.rounds > 0 {
+            settle().await;
+            tokio::time::advance(self.config.services[0].interval()).await;
+        }
+        self.rounds += 1;
+        for service in &self.config.services {
+            wait_for_checks(&self.config, &service.id, self.rounds).await;
+        }
+        load_latest(&self.config.latest_path()).await.unwrap()
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn healthy_servers_reach_the_dashboard_files() {
+    let terraria = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("healthy");
+    let config = config(&terraria, &hytale, &dir);
+    let mut monitor = Running::start(&config);
+
+    let entries = monitor.round().await;
+    let (terraria_entry, hytale_entry) = (&entries[0], &entries[1]);
+
+    assert_eq!(terraria_entry.service_id, "terraria");
+    assert_eq!(terraria_entry.is_online, Some(true));
+    assert_eq!(terraria_entry.version.as_deref(), Some("1.4.4.9"));
+    assert_eq!(terraria_entry.failure, None);
+    assert_eq!(hytale_entry.is_online, Some(true));
+    assert_eq!(hytale_entry.loss_percent, Some(0.0));
+
+    assert_eq!(history_online(&config, "terraria"), vec!["true"]);
+    assert_eq!(history_online(&config, "hytale"), vec!["true"]);
+
+    // The MTU diagnostic the first check called for runs once it is recorded.
+    // Its probes of each size are spaced apart, so the clock moves in steps
+    assert_eq!(hytale_entry.mtu, None);
+    for _ in 0..20 {
+        settle().await;
+        tokio::time::advance(Duration::from_millis(50)).await;
+    }
+    let entries = monitor.round().await;
+    assert!(entries[1].mtu.as_ref().is_some_and(|mtu| !mtu.problem));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn terraria_outage_is_recorded_and_recovers() {
+    let terraria = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("outage");
+    let config = config(&terraria, &hytale, &dir);
+    let mut monitor = Running::start(&config);
+
+    monitor.round().await;
+    terraria.refuse_connections(true);
+    let down = monitor.round().await.remove(0);
+    terraria.refuse_connections(false);
+    let up = monitor.round().await.remove(0);
+
+    assert_eq!(down.is_online, Some(false));
+    assert_eq!(down.failure.as_deref(), Some("Connection refused"));
+    assert_eq!(up.is_online, Some(true));
+    assert_eq!(history_online(&config, "terraria"), vec!["true", "false", "true"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn terraria_release_is_discovered_once() {
+    let terraria =
+        terraria::MockResponder::new(terraria::MockReply::Accept(0)).release(277).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("discovery");
+    let mut monitor = Running::start(&config(&terraria, &hytale, &dir));
+
+    let first = monitor.round().await.remove(0);
+    let connects_after_discovery = terraria.connect_releases().len();
+    let second = monitor.round().await.remove(0);
+
+    assert_eq!(first.is_online, Some(true));
+    assert_eq!(first.version.as_deref(), Some("1.4.4.8"));
+    // Later checks go straight to the discovered release
+    assert_eq!(terraria.connect_releases()[connects_after_discovery..], [Some(277)]);
+    assert_eq!(second.version.as_deref(), Some("1.4.4.8"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn unknown_terraria_release_is_not_searched_every_check() {
+    // Newer than anything discovery tries
+    let terraria =
+        terraria::MockResponder::new(terraria::MockReply::Accept(0)).release(400).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("unknown-release");
+    let mut monitor = Running::start(&config(&terraria, &hytale, &dir));
+
+    let first = monitor.round().await.remove(0);
+    let connects_after_discovery = terraria.connect_releases().len();
+    monitor.round().await;
+
+    // The server answers, if only to reject us, so it is online
+    assert_eq!(first.is_online, Some(true));
+    assert!(connects_after_discovery > 2);
+    // The next check backs off instead of searching again
+    assert_eq!(terraria.connect_releases().len(), connects_after_discovery + 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn terraria_deep_probe_records_the_world() {
+    let world = WorldInfo {
+        name: "Bananil".to_string(),
+        width: 8400,
+        height: 2400,
+        time: 0,
+        is_day: false,
+        moon_phase: 0,
+        difficulty: Difficulty::Journey,
+        hardmode: Some(true),
+        events: Vec::new(),
+    };
+    let terraria = terraria::MockResponder::new(terraria::MockReply::World(world)).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("world");
+    let mut config = config(&terraria, &hytale, &dir);
+    config.services[0].checker = terraria_checker(true);
+    let mut monitor = Running::start(&config);
+
+    let latest = monitor.round().await;
+
+    let world = latest[0].world.as_ref().unwrap();
+    assert_eq!((world.name.as_str(), world.size.as_str(), world.time.as_str()), ("Bananil", "Large", "19:30"));
+    assert_eq!(world.hardmode, Some(true));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn hytale_version_mismatch_is_offline_with_the_reason() {
+    let terraria = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::VersionNegotiation(vec![QUIC_V2]))
+        .start()
+        .await
+        .unwrap();
+    let dir = temp_dir("mismatch");
+    let mut config = config(&terraria, &hytale, &dir);
+    config.services[1].probe_all_addresses = true;
+    let mut monitor = Running::start(&config);
+
+    let entry = monitor.round().await.remove(1);
+
+    assert_eq!(entry.is_online, Some(false));
+    let failure = entry.failure.unwrap_or_default();
+    assert!(failure.starts_with("Version Negotiation"), "unexpected failure: {}", failure);
+    assert_eq!(entry.addresses.len(), 1);
+    assert_eq!(history_online(&config, "hytale"), vec!["false"]);
+    assert!(!hytale.received().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn every_configured_service_is_checked() {
+    let first = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let second = terraria::MockResponder::new(terraria::MockReply::Close).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("services");
+    let config: MonitorConfig = format!(
+        r#"
+        history_path = "{}"
+
+        [[service]]
+        id = "hytale"
+        name = "Hytale"
+        kind = "hytale"
+        host = "127.0.0.1"
+        port = {}
+        burst = 1
+
+        [[service]]
+        id = "terraria"
+        name = "Terraria"
+        kind = "terraria"
+        host = "127.0.0.1"
+        port = {}
+        deep_probe = false
+
+        [[service]]
+        id = "terraria-expert"
+        name = "Terraria (Expert)"
+        kind = "terraria"
+        host = "127.0.0.1"
+        port = {}
+        deep_probe = false
+        "#,
+        dir.join("status-history.csv").display(),
+        hytale.port(),
+        first.port(),
+        second.port()
+    )
+    .parse()
+    .unwrap();
+    let mut monitor = Running::start(&config);
+
+    let entries = monitor.round().await;
+
+    let ids: Vec<&str> = entries.iter().map(|entry| entry.service_id.as_str()).collect();
+    assert_eq!(ids, vec!["hytale", "terraria", "terraria-expert"]);
+    assert_eq!(entries[2].service_name, "Terraria (Expert)");
+    assert_eq!(entries.iter().map(|entry| entry.is_online).collect::<Vec<_>>(), vec![Some(true), Some(true), Some(false)]);
+    assert!(!dir.join("status-latest.json.tmp").exists(), "temporary file left behind");
+    let history = load_history(&config.history_path).await.unwrap();
+    assert_eq!(history.services.keys().collect::<Vec<_>>(), vec!["hytale", "terraria", "terraria-expert"]);
+    assert_eq!(history_online(&config, "terraria-expert"), vec!["false"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn slow_checks_time_out() {
+    let terraria = terraria::MockResponder::new(terraria::MockReply::Stall).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("timeout");
+    let mut config = config(&terraria, &hytale, &dir);
+    config.services[0].timeout_secs = Some(1);
+    let _monitor = Running::start(&config);
+
+    // The clock only moves once the mock has the request it stalls on
+    while !has_checks(&config, "terraria", 1) {
+        settle().await;
+        tokio::time::advance(Duration::from_millis(250)).await;
+    }
+    let entry = load_latest(&config.latest_path()).await.unwrap().remove(0);
+
+    // The checker's own deadline is the service's timeout, and names what it was waiting for
+    assert_eq!(entry.is_online, Some(false));
+    assert_eq!(entry.failure.as_deref(), Some("Port open but no game reply (Timed out)"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn services_run_on_their_own_schedule() {
+    let hung = terraria::MockResponder::new(terraria::MockReply::Stall).start().await.unwrap();
+    let healthy = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let dir = temp_dir("schedule");
+    let service = |id: &str, port: u16| ServiceConfig {
+        interval_secs: 1,
+        timeout_secs: Some(3),
+        ..ServiceConfig::new(id, id, "127.0.0.1", port, terraria_checker(false))
+    };
+    let config = MonitorConfig {
+        history_path: dir.join("status-history.csv"),
+        sqlite: None,
+        services: vec![service("hung", hung.port()), service("healthy", healthy.port())],
+    };
+
+    let monitor = tokio::spawn(run_monitor(config.clone()));
+    // Checked at 0s, 1s and 2s while the hung server's first check is still running
+    for count in 1..=3 {
+        wait_for_checks(&config, "healthy", count).await;
+        if count < 3 {
+            tokio::time::advance(Duration::from_secs(1)).await;
+        }
+    }
+
+    assert!(!monitor.is_finished(), "the monitor should run until stopped");
+    monitor.abort();
+    assert_eq!(history_online(&config, "healthy"), vec!["true", "true", "true"]);
+    assert!(history_online(&config, "hung").is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn write_failures_do_not_stop_the_monitor() {
+    let healthy = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let dir = temp_dir("unwritable");
+    std::fs::create_dir_all(&dir).unwrap();
+    // A file where the history and latest files' directory should be
+    std::fs::write(dir.join("data"), "").unwrap();
+    let config = MonitorConfig {
+        history_path: dir.join("data").join("status-history.csv"),
+        sqlite: None,
+        services: vec![ServiceConfig {
+            interval_secs: 1,
+            ..ServiceConfig::new("terraria", "Terraria", "127.0.0.1", healthy.port(), terraria_checker(false))
+        }],
+    };
+
+    let monitor = tokio::spawn(run_monitor(config));
+    // Two checks, neither of which can be written
+    settle().await;
+    tokio::time::advance(Duration::from_secs(1)).await;
+    settle().await;
+
+    assert!(!monitor.is_finished(), "the monitor should keep running");
+    monitor.abort();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn legacy_history_is_migrated() {
+    let terraria = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let hytale = hytale::MockResponder::new(hytale::MockReply::Initial).start().await.unwrap();
+    let dir = temp_dir("migration");
+    let config = config(&terraria, &hytale, &dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let legacy = format!(
+        "{}\n2026-01-01T00:00:00+00:00,true,35,false,0\n2026-01-01T00:01:00+00:00,,0,true,48\n",
+        LEGACY_HISTORY_HEADER
+    );
+    std::fs::write(&config.history_path, legacy).unwrap();
+    let mut monitor = Running::start(&config);
+
+    monitor.round().await;
+
+    assert_eq!(history_online(&config, "terraria"), vec!["true", "", "true"]);
+    assert_eq!(history_online(&config, "hytale"), vec!["false", "true", "true"]);
+    let rows = history_rows(&config);
+    assert_eq!(rows[0], vec!["2026-01-01T00:00:00+00:00", "hytale", "false", "0"]);
+    assert_eq!(rows[3], vec!["2026-01-01T00:01:00+00:00", "terraria", "", "0"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use chrono::{Duration, TimeZone, Utc};
+use status_monitor::sqlite::SqliteHistory;
+use status_monitor::{load_history, run_monitor, CheckerConfig, MonitorConfig, ServiceConfig, ServiceStatus, SqliteConfig};
+use terraria_health_checker::mock as terraria;
+
+/// A fresh database path for one test.
+fn database_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("status-sqlite-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("status-history.db")
+}
+
+/// `count` checks of service `id`, a minute apart. Online every other one,
+/// except every fifth which is inconclusive.
+fn checks(id: &str, count: usize) -> Vec<ServiceStatus> {
+    let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    (0..count)
+        .map(|i| ServiceStatus {
+            service_id: id.to_string(),
+            service_name: id.to_string(),
+            is_online: if i % 5 == 4 { None } else { Some(i % 2 == 0) },
+            latency_ms: 10 * i as u128,
+            timestamp: start + Duration::minutes(i as i64),
+            failure: (i % 2 == 1).then(|| "Timed out".to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn range_returns_the_checks_of_one_service() {
+    let history = SqliteHistory::open(&database_path("range")).unwrap();
+    let terraria = checks("terraria", 10);
+    history.insert(&terraria).await.unwrap();
+    history.insert(&checks("hytale", 10)).await.unwrap();
+
+    let range = history.range("terraria", terraria[2].timestamp, terraria[6].timestamp).await.unwrap();
+    assert_eq!(range, terraria[2..6]);
+}
+
+#[tokio::test]
+async fn uptime_leaves_out_inconclusive_checks() {
+    let history = SqliteHistory::open(&database_path("uptime")).unwrap();
+    let entries = checks("terraria", 10);
+    history.insert(&entries).await.unwrap();
+
+    let uptime = history.uptime("terraria", entries[0].timestamp, entries[9].timestamp).await.unwrap();
+    // Checks 0..9: 4 and 9 would be inconclusive, only 4 is in the window; 0, 2, 6 and 8 are online
+    assert_eq!(uptime.checks, 9);
+    assert_eq!(uptime.no_data, 1);
+    assert_eq!(uptime.online, 4);
+    assert_eq!(uptime.percent(), Some(50.0));
+    assert_eq!(uptime.avg_latency_ms, Some(40.0));
+
+    let unknown = history.uptime("hytale", entries[0].timestamp, entries[9].timestamp).await.unwrap();
+    assert_eq!(unknown.checks, 0);
+    assert_eq!(unknown.percent(), None);
+}
+
+#[tokio::test]
+async fn prune_deletes_expired_checks() {
+    let path = database_path("prune");
+    let history = SqliteHistory::open(&path).unwrap();
+    let entries = checks("terraria", 10);
+    history.insert(&entries).await.unwrap();
+
+    assert_eq!(history.prune(entries[3].timestamp).await.unwrap(), 3);
+
+    // Survives reopening the database
+    let history = SqliteHistory::open(&path).unwrap();
+    let latest = history.history(4).await.unwrap();
+    assert_eq!(latest.get("terraria"), &entries[6..]);
+    let all = history.range("terraria", entries[0].timestamp, entries[9].timestamp + Duration::minutes(1)).await;
+    assert_eq!(all.unwrap(), entries[3..]);
+}
+
+#[tokio::test]
+async fn database_failures_do_not_stop_the_monitor() {
+    let healthy = terraria::MockResponder::new(terraria::MockReply::Accept(0)).start().await.unwrap();
+    let path = database_path("unopenable");
+    let dir = path.parent().unwrap().to_path_buf();
+    let config = MonitorConfig {
+        history_path: dir.join("status-history.csv"),
+        // A directory can't be opened as a database
+        sqlite: Some(SqliteConfig::new(&dir)),
+        services: vec![ServiceConfig {
+            interval_secs: 1,
+            ..ServiceConfig::new("terraria", "Terraria", "127.0.0.1", healthy.port(), CheckerConfig::Terraria {
+                deep_probe: false,
+            })
+        }],
+    };
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let stopped = tokio::time::timeout(std::time::Duration::from_millis(1500), run_monitor(config.clone())).await;
+
+    assert!(stopped.is_err(), "the monitor should keep running");
+    assert!(!load_history(&config.history_path).await.unwrap().get("terraria").is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
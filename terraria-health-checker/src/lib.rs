@@ -1,45 +1,790 @@
-use std::net::{TcpStream, ToSocketAddrs};
-use std::time::{Duration, Instant};
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod protocol;
+mod tshock;
+mod version;
+mod world;
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
 use std::io;
+use std::fmt;
+
+use probe_capture::{CapturedStream, TcpFlow};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::{timeout_at, Instant};
+
+use protocol::{
+    ClientUuid, ConnectRequest, Disconnect, Message, NetworkText, NetworkTextMode, PlayerInfo,
+    RequestWorldData, SetUserSlot,
+};
+
+pub use version::{
+    KNOWN_RELEASES, RELEASE_SCAN_AHEAD, ServerVersion, release_for_version, version_for_release,
+};
+pub use health_check::{CheckResult, HealthCheck, ProbeOutcome};
+pub use probe_capture::Capture;
+pub use tshock::{fetch_tshock_status, TShockConfig, TShockError, TShockStatus, DEFAULT_REST_PORT};
+pub use world::{Difficulty, WorldEvent, WorldInfo, WorldSize};
+
+/// Character name used when a deep probe logs in as a guest.
+pub const PROBE_PLAYER_NAME: &str = "HealthCheck";
+
+/// Release number sent in the ConnectRequest (`Terraria279` is 1.4.4.9).
+pub const TERRARIA_RELEASE: u32 = 279;
 
 pub struct ServerInfo {
     pub is_online: bool,
     pub latency_ms: u128,
+    /// How far the check got, and why it stopped if the server did not answer.
+    pub outcome: ProbeOutcome,
+    /// How the server answered our ConnectRequest, if a TCP connection was made.
+    pub handshake: Option<HandshakeOutcome>,
+    /// The version the server accepts, when the handshake revealed it.
+    pub server_version: Option<ServerVersion>,
+    /// World details, only filled by a deep probe that got past the handshake.
+    pub world: Option<WorldInfo>,
+    /// Player list and server state from TShock's REST API, when it is enabled.
+    pub tshock: Option<TShockStatus>,
+    /// Result for every resolved address, when checked with `probe_all_addresses`.
+    pub addresses: Vec<AddressResult>,
+}
+
+/// Check result for one of the addresses a host name resolved to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressResult {
+    pub address: SocketAddr,
+    pub is_online: bool,
+    pub outcome: ProbeOutcome,
+    pub latency_ms: u128,
+}
+
+impl AddressResult {
+    /// `"IPv4"` or `"IPv6"`.
+    pub fn family(&self) -> &'static str {
+        if self.address.is_ipv4() { "IPv4" } else { "IPv6" }
+    }
+}
+
+/// The server's reply to a ConnectRequest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandshakeOutcome {
+    /// SetUserSlot: the server accepted us and assigned a player slot.
+    Accepted { player_slot: u8 },
+    /// RequestPassword: the server is alive but password protected.
+    PasswordRequired,
+    /// Disconnect: the server is alive but refused us.
+    Rejected(DisconnectReason),
+    /// The server replied with a message we did not expect at this stage.
+    Unexpected { msg_type: u8 },
+    /// The port accepted the connection but nothing came back (hung server).
+    NoReply,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectReason {
+    VersionMismatch,
+    ServerFull,
+    Banned,
+    /// Any other reason, as literal text or localization key.
+    Other(String),
+}
+
+impl HandshakeOutcome {
+    /// Whether the reply proves the game server itself is running.
+    pub fn is_alive(&self) -> bool {
+        !matches!(self, HandshakeOutcome::NoReply)
+    }
+}
+
+impl ServerInfo {
+    fn offline(outcome: ProbeOutcome) -> Self {
+        Self {
+            is_online: false,
+            latency_ms: 0,
+            outcome,
+            handshake: None,
+            server_version: None,
+            world: None,
+            tshock: None,
+            addresses: Vec::new(),
+        }
+    }
 }
 
-/// Checks if a Terraria server is reachable via TCP.
+impl DisconnectReason {
+    fn from_network_text(reason: &NetworkText) -> Self {
+        // Vanilla servers send localization keys, TShock and plugins usually send literal text
+        if reason.mode == NetworkTextMode::LocalizationKey {
+            match reason.text.as_str() {
+                "LegacyMultiplayer.3" => return DisconnectReason::Banned,
+                "LegacyMultiplayer.4" => return DisconnectReason::VersionMismatch,
+                "CLI.ServerIsFull" => return DisconnectReason::ServerFull,
+                _ => {}
+            }
+        }
+
+        let lower = reason.text.to_lowercase();
+        if lower.contains("banned") {
+            DisconnectReason::Banned
+        } else if lower.contains("full") {
+            DisconnectReason::ServerFull
+        } else if lower.contains("version") {
+            DisconnectReason::VersionMismatch
+        } else {
+            DisconnectReason::Other(reason.to_string())
+        }
+    }
+}
+
+impl fmt::Display for HandshakeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeOutcome::Accepted { player_slot } => write!(f, "Accepted (slot {})", player_slot),
+            HandshakeOutcome::PasswordRequired => write!(f, "Password required"),
+            HandshakeOutcome::Rejected(reason) => write!(f, "Rejected: {}", reason),
+            HandshakeOutcome::Unexpected { msg_type } => write!(f, "Unexpected reply (message {})", msg_type),
+            HandshakeOutcome::NoReply => write!(f, "Port open but no game reply"),
+        }
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::VersionMismatch => write!(f, "version mismatch"),
+            DisconnectReason::ServerFull => write!(f, "server full"),
+            DisconnectReason::Banned => write!(f, "banned"),
+            DisconnectReason::Other(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Which resolved addresses a check tries, and in what order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressFamily {
+    /// Every address, in the order the resolver returned them.
+    #[default]
+    Any,
+    /// IPv4 addresses first, then IPv6.
+    PreferIpv4,
+    /// IPv6 addresses first, then IPv4.
+    PreferIpv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl AddressFamily {
+    fn order(self, addrs: impl Iterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = match self {
+            AddressFamily::Ipv4Only => addrs.filter(SocketAddr::is_ipv4).collect(),
+            AddressFamily::Ipv6Only => addrs.filter(SocketAddr::is_ipv6).collect(),
+            _ => addrs.collect(),
+        };
+        // Stable sort keeps the resolver's order within each family
+        match self {
+            AddressFamily::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            AddressFamily::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+            _ => {}
+        }
+        addrs
+    }
+}
+
+/// Settings for [`check_terraria_status`].
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Time allowed for the TCP connect to each resolved address.
+    pub connect_timeout: Duration,
+    /// Upper bound for the whole check: DNS, connects, handshake and world reads.
+    pub deadline: Duration,
+    pub address_family: AddressFamily,
+    /// Release number announced in the ConnectRequest.
+    pub release: u32,
+    /// Continue as a guest after the handshake and read WorldInfo.
+    pub deep: bool,
+    /// Check every resolved address concurrently instead of stopping at the
+    /// first that connects, and report each in [`ServerInfo::addresses`].
+    pub probe_all_addresses: bool,
+    /// Records every connection attempt and the bytes exchanged, for Wireshark.
+    pub capture: Option<Capture>,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(3),
+            deadline: Duration::from_secs(6),
+            address_family: AddressFamily::Any,
+            release: TERRARIA_RELEASE,
+            deep: false,
+            probe_all_addresses: false,
+            capture: None,
+        }
+    }
+}
+
+/// Checks if a Terraria server is reachable and answering the game protocol.
+///
+/// Connects over TCP, sends a ConnectRequest and classifies the reply, so a
+/// hung server that still holds the port is reported as offline.
+///
+/// Blocking wrapper around [`check_terraria_status`] with default options;
+/// don't call it from inside a tokio runtime.
 ///
 /// # Arguments
 /// * `host` - The IP address or hostname of the server.
 /// * `port` - The port number (usually 7777).
 ///
 /// # Returns
-/// * `Ok(ServerInfo)` if the check completed (successfully connected or refused).
-/// * `Err(e)` if there was a DNS resolution error.
+/// * `Ok(ServerInfo)` if the check ran; `outcome` tells why an offline server is offline.
+/// * `Err(e)` if the runtime for the check could not be started.
 pub fn check_server_status(host: &str, port: u16) -> io::Result<ServerInfo> {
-    // specific string formatting for the socket address
-    let address = format!("{}:{}", host, port);
+    check_server_status_with_release(host, port, TERRARIA_RELEASE)
+}
 
-    // Set a timeout so the CLI doesn't hang if the server is down
-    let timeout = Duration::from_secs(3);
+/// Same as [`check_server_status`], but announces `release` in the ConnectRequest.
+///
+/// Use this with a release found by [`discover_server_version`] so servers
+/// running another game version still accept the probe.
+pub fn check_server_status_with_release(host: &str, port: u16, release: u32) -> io::Result<ServerInfo> {
+    let options = CheckOptions { release, ..Default::default() };
+    block_on(async { Ok(check_terraria_status(host, port, &options).await) })
+}
+
+/// Deep probe: after a successful handshake, continues as a guest player and
+/// reads the server's WorldInfo (name, size, time, difficulty, events).
+///
+/// Password-protected servers stop at the handshake, so `world` stays `None`.
+pub fn check_server_status_deep(host: &str, port: u16, release: u32) -> io::Result<ServerInfo> {
+    let options = CheckOptions { release, deep: true, ..Default::default() };
+    block_on(async { Ok(check_terraria_status(host, port, &options).await) })
+}
 
-    // Resolve the address first
-    let socket_addrs = address.to_socket_addrs()?;
+/// Async Terraria check, configured by `options`.
+///
+/// Tries the resolved addresses in the order given by `options.address_family`
+/// until one accepts the connection, then performs the handshake (and the
+/// world read for a deep check) on it. Anything still pending when
+/// `options.deadline` runs out counts as no reply.
+///
+/// With `options.probe_all_addresses`, every address is checked concurrently
+/// instead; the returned info is that of the first address (in preference
+/// order) that answered, with all results listed in `addresses`.
+///
+/// Never fails: DNS errors, refused connections and silent servers are all
+/// reported through [`ServerInfo::outcome`].
+pub async fn check_terraria_status(host: &str, port: u16, options: &CheckOptions) -> ServerInfo {
+    let deadline = Instant::now() + options.deadline;
+    let addrs = match resolve(host, port, options, deadline).await {
+        Ok(addrs) => addrs,
+        Err(e) => return ServerInfo::offline(ProbeOutcome::DnsFailure(e.to_string())),
+    };
 
-    // Attempt to connect to any resolved IP
-    for addr in socket_addrs {
+    if options.probe_all_addresses {
+        probe_each_address(&addrs, options, deadline).await
+    } else {
+        probe_addresses(&addrs, options, deadline).await
+    }
+}
+
+/// Checks every one of `addrs` concurrently. Returns the info of the first
+/// (in `addrs` order) that answered, with all results listed in `addresses`.
+async fn probe_each_address(addrs: &[SocketAddr], options: &CheckOptions, deadline: Instant) -> ServerInfo {
+    let mut probes = JoinSet::new();
+    for (index, addr) in addrs.iter().copied().enumerate() {
+        let options = options.clone();
+        probes.spawn(async move { (index, probe_addresses(&[addr], &options, deadline).await) });
+    }
+    let mut results = probes.join_all().await;
+    results.sort_by_key(|(index, _)| *index);
+
+    let addresses = results
+        .iter()
+        .zip(addrs)
+        .map(|((_, info), address)| AddressResult {
+            address: *address,
+            is_online: info.is_online,
+            outcome: info.outcome.clone(),
+            latency_ms: info.latency_ms,
+        })
+        .collect();
+    // Never empty, resolve() fails instead of returning no address
+    let mut primary = results
+        .into_iter()
+        .map(|(_, info)| info)
+        .reduce(|best, info| if !best.is_online && info.is_online { info } else { best })
+        .expect("at least one resolved address");
+    primary.addresses = addresses;
+    primary
+}
+
+/// Connects to the first of `addrs` that accepts and runs the handshake on it.
+async fn probe_addresses(addrs: &[SocketAddr], options: &CheckOptions, deadline: Instant) -> ServerInfo {
+    let (mut stream, latency_ms) = match connect_any(addrs, options, deadline).await {
+        Ok(connected) => connected,
+        Err(outcome) => return ServerInfo::offline(outcome),
+    };
+
+    let (handshake, server_version, outcome) =
+        match timeout_at(deadline, perform_handshake(&mut stream, options.release)).await {
+            Ok(Ok((HandshakeOutcome::Unexpected { msg_type }, _))) => (
+                HandshakeOutcome::Unexpected { msg_type },
+                None,
+                ProbeOutcome::ProtocolError(format!("unexpected reply (message {})", msg_type)),
+            ),
+            Ok(Ok((handshake, server_version))) => (handshake, server_version, ProbeOutcome::Answered),
+            Ok(Err(e)) => (HandshakeOutcome::NoReply, None, ProbeOutcome::from_io_error(&e)),
+            Err(_) => (HandshakeOutcome::NoReply, None, ProbeOutcome::Timeout),
+        };
+    let world = match handshake {
+        HandshakeOutcome::Accepted { player_slot } if options.deep => {
+            timeout_at(deadline, request_world_info(&mut stream, player_slot)).await.ok().and_then(Result::ok)
+        }
+        _ => None,
+    };
+
+    ServerInfo {
+        is_online: handshake.is_alive(),
+        latency_ms,
+        outcome,
+        handshake: Some(handshake),
+        server_version,
+        world,
+        tshock: None,
+        addresses: Vec::new(),
+    }
+}
+
+/// Checks a TShock server: the plain handshake probe, configured by `options`,
+/// plus TShock's REST status (player list, max players, world, uptime).
+///
+/// Degrades to the plain probe, with `tshock` left `None`, when REST is not
+/// enabled, unreachable, rejects the token or doesn't answer within `options.deadline`.
+///
+/// Like [`check_terraria_status`], failures are reported through [`ServerInfo::outcome`].
+pub async fn check_tshock_status(host: &str, port: u16, options: &CheckOptions, config: &TShockConfig) -> ServerInfo {
+    let rest = tokio::time::timeout(options.deadline, fetch_tshock_status(host, config));
+    let (mut info, rest) = tokio::join!(check_terraria_status(host, port, options), rest);
+
+    info.tshock = rest.ok().and_then(Result::ok);
+    info
+}
+
+/// A Terraria server to check through the common [`HealthCheck`] interface.
+#[derive(Debug, Clone)]
+pub struct TerrariaCheck {
+    pub host: String,
+    pub port: u16,
+    pub options: CheckOptions,
+}
+
+impl TerrariaCheck {
+    pub fn new(host: impl Into<String>, port: u16, options: CheckOptions) -> Self {
+        Self { host: host.into(), port, options }
+    }
+}
+
+#[health_check::async_trait]
+impl HealthCheck for TerrariaCheck {
+    fn name(&self) -> &str {
+        "Terraria"
+    }
+
+    async fn check(&self) -> CheckResult {
+        CheckResult::from(&check_terraria_status(&self.host, self.port, &self.options).await)
+    }
+}
+
+impl From<&ServerInfo> for CheckResult {
+    fn from(info: &ServerInfo) -> Self {
+        let failure = match (&info.outcome, &info.handshake) {
+            (ProbeOutcome::Answered, _) => None,
+            (outcome, Some(handshake)) => Some(format!("{} ({})", handshake, outcome)),
+            (outcome, None) => Some(outcome.to_string()),
+        };
+        CheckResult {
+            is_online: info.is_online,
+            latency_ms: info.latency_ms,
+            outcome: info.outcome.clone(),
+            failure,
+            version: info.server_version.as_ref().and_then(|v| {
+                v.version.clone().or_else(|| v.release.map(|r| format!("release {}", r)))
+            }),
+        }
+    }
+}
+
+/// Finds out which game version a Terraria server accepts.
+///
+/// Uses the version hint in the server's mismatch reply when there is one,
+/// otherwise retries the handshake with every known release number (then a
+/// range of newer, unknown ones) until the server stops rejecting us.
+///
+/// Blocking wrapper around [`discover_terraria_version`] with default options.
+///
+/// # Returns
+/// * `Ok(Some(version))` if the server accepted a release or named its version.
+/// * `Ok(None)` if the server is unreachable or refused us for another reason.
+/// * `Err(e)` if there was a DNS resolution error.
+pub fn discover_server_version(host: &str, port: u16) -> io::Result<Option<ServerVersion>> {
+    block_on(discover_terraria_version(host, port, &CheckOptions::default()))
+}
+
+/// Async version of [`discover_server_version`].
+///
+/// `options.deadline` applies to each handshake attempt rather than the whole
+/// discovery, which may take dozens of attempts. `options.release` and
+/// `options.deep` are ignored.
+pub async fn discover_terraria_version(
+    host: &str,
+    port: u16,
+    options: &CheckOptions,
+) -> io::Result<Option<ServerVersion>> {
+    let addrs = resolve(host, port, options, Instant::now() + options.deadline).await?;
+
+    for release in version::discovery_candidates() {
+        let deadline = Instant::now() + options.deadline;
+        // Every attempt needs a fresh connection, the server drops us after rejecting
+        let Ok((mut stream, _)) = connect_any(&addrs, options, deadline).await else {
+            return Ok(None);
+        };
+
+        match timeout_at(deadline, perform_handshake(&mut stream, release)).await {
+            Ok(Ok((HandshakeOutcome::Rejected(DisconnectReason::VersionMismatch), None))) => continue,
+            Ok(Ok((_, server_version))) => return Ok(server_version),
+            Ok(Err(_)) | Err(_) => return Ok(None),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Runs `future` to completion on a throwaway single-threaded runtime.
+fn block_on<T>(future: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
+
+/// Resolves `host`, keeping only the addresses `options.address_family` allows.
+async fn resolve(host: &str, port: u16, options: &CheckOptions, deadline: Instant) -> io::Result<Vec<SocketAddr>> {
+    let addrs = match timeout_at(deadline, lookup_host((host, port))).await {
+        Ok(addrs) => options.address_family.order(addrs?),
+        Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "DNS resolution timed out")),
+    };
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "No address resolved"));
+    }
+    Ok(addrs)
+}
+
+/// A probe's TCP connection, recorded when the options ask for a capture.
+type Connection = CapturedStream<TcpStream>;
+
+/// Connects to the first address that accepts, returning the stream and the connect latency,
+/// or why the last address failed.
+async fn connect_any(
+    addrs: &[SocketAddr],
+    options: &CheckOptions,
+    deadline: Instant,
+) -> Result<(Connection, u128), ProbeOutcome> {
+    let mut last_failure = ProbeOutcome::Timeout;
+    for addr in addrs {
         let start = Instant::now();
-        if TcpStream::connect_timeout(&addr, timeout).is_ok() {
-            let latency = start.elapsed().as_millis();
-            return Ok(ServerInfo {
-                is_online: true,
-                latency_ms: latency,
-            });
+        let connect_by = deadline.min(start + options.connect_timeout);
+        match timeout_at(connect_by, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => {
+                let latency_ms = start.elapsed().as_millis();
+                let flow = capture_flow(options, stream.local_addr().ok(), *addr).map(|mut flow| {
+                    flow.established();
+                    flow
+                });
+                return Ok((CapturedStream::new(stream, flow), latency_ms));
+            }
+            Ok(Err(e)) => {
+                last_failure = ProbeOutcome::from_io_error(&e);
+                if let Some(mut flow) = capture_flow(options, None, *addr) {
+                    match last_failure {
+                        ProbeOutcome::Refused => flow.refused(),
+                        _ => flow.unanswered(),
+                    }
+                }
+            }
+            Err(_) => {
+                last_failure = ProbeOutcome::Timeout;
+                if let Some(mut flow) = capture_flow(options, None, *addr) {
+                    flow.unanswered();
+                }
+            }
+        }
+    }
+    Err(last_failure)
+}
+
+/// Starts recording a connection to `remote` if the options ask for a
+/// capture. `local` is unknown for connects that failed.
+fn capture_flow(options: &CheckOptions, local: Option<SocketAddr>, remote: SocketAddr) -> Option<TcpFlow> {
+    let capture = options.capture.as_ref()?;
+    let wildcard = match remote {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let local = probe_capture::local_addr_for(local.unwrap_or(wildcard), remote);
+    Some(capture.tcp_flow(local, remote))
+}
+
+/// Sends a ConnectRequest for `release` and classifies the reply, along with
+/// the server version it implies. Fails if the connection broke before a reply.
+async fn perform_handshake(
+    stream: &mut Connection,
+    release: u32,
+) -> io::Result<(HandshakeOutcome, Option<ServerVersion>)> {
+    stream.write_all(&ConnectRequest::for_release(release).encode()).await?;
+    let (msg_type, payload) = protocol::read_packet_async(stream).await?;
+
+    let reply = match msg_type {
+        // Getting past the version check means the server runs the release we announced
+        protocol::SET_USER_SLOT => match SetUserSlot::decode(&payload) {
+            Ok(slot) => (
+                HandshakeOutcome::Accepted { player_slot: slot.player_slot },
+                Some(ServerVersion::from_release(release)),
+            ),
+            Err(_) => (HandshakeOutcome::Unexpected { msg_type }, None),
+        },
+        protocol::REQUEST_PASSWORD => (HandshakeOutcome::PasswordRequired, Some(ServerVersion::from_release(release))),
+        protocol::DISCONNECT => match Disconnect::decode(&payload) {
+            Ok(Disconnect { reason }) => {
+                let reason_kind = DisconnectReason::from_network_text(&reason);
+                let server_version = match reason_kind {
+                    DisconnectReason::VersionMismatch => {
+                        version::parse_version_hint(&reason.to_string()).map(ServerVersion::from_version)
+                    }
+                    _ => None,
+                };
+                (HandshakeOutcome::Rejected(reason_kind), server_version)
+            }
+            Err(_) => (HandshakeOutcome::Unexpected { msg_type }, None),
+        },
+        _ => (HandshakeOutcome::Unexpected { msg_type }, None),
+    };
+    Ok(reply)
+}
+
+/// Logs in as a guest in `player_slot` and waits for the WorldInfo reply.
+async fn request_world_info(stream: &mut Connection, player_slot: u8) -> io::Result<WorldInfo> {
+    let uuid = ClientUuid { uuid: "00000000-0000-0000-0000-000000000000".to_string() };
+
+    let mut request = PlayerInfo::guest(player_slot, PROBE_PLAYER_NAME).encode();
+    request.extend(uuid.encode());
+    request.extend(RequestWorldData.encode());
+    stream.write_all(&request).await?;
+
+    // The server may interleave other sync messages before WorldInfo
+    for _ in 0..32 {
+        let (msg_type, payload) = protocol::read_packet_async(stream).await?;
+        match msg_type {
+            protocol::WORLD_INFO => return WorldInfo::decode(&payload),
+            protocol::DISCONNECT => {
+                let Disconnect { reason } = Disconnect::decode(&payload)?;
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, reason.to_string()));
+            }
+            _ => continue,
         }
     }
 
-    Ok(ServerInfo {
-        is_online: false,
-        latency_ms: 0,
-    })
+    Err(io::Error::new(io::ErrorKind::InvalidData, "No WorldInfo received"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::RequestPassword;
+    use tokio::net::TcpListener;
+
+    /// A server on a local port that reads one ConnectRequest and answers
+    /// with `reply`, or closes the connection when `reply` is `None`.
+    async fn serve_once(reply: Option<Vec<u8>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            protocol::read_packet_async(&mut socket).await.unwrap();
+            if let Some(reply) = reply {
+                socket.write_all(&reply).await.unwrap();
+                // Hold the connection until the probe hangs up
+                let _ = protocol::read_packet_async(&mut socket).await;
+            }
+        });
+        port
+    }
+
+    /// A server on a local port that only accepts `release`, rejecting
+    /// every other one the way a vanilla server does.
+    async fn serve_release(release: u32) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let (_, payload) = protocol::read_packet_async(&mut socket).await.unwrap();
+                let reply = match ConnectRequest::decode(&payload).unwrap().release() {
+                    Some(announced) if announced == release => {
+                        SetUserSlot { player_slot: 0, check_bytes_in_client_loop: Some(false) }.encode()
+                    }
+                    _ => disconnect(NetworkText::key("LegacyMultiplayer.4")),
+                };
+                socket.write_all(&reply).await.unwrap();
+            }
+        });
+        port
+    }
+
+    async fn check(reply: Option<Vec<u8>>) -> ServerInfo {
+        let port = serve_once(reply).await;
+        check_terraria_status("127.0.0.1", port, &CheckOptions::default()).await
+    }
+
+    fn disconnect(reason: NetworkText) -> Vec<u8> {
+        Disconnect { reason }.encode()
+    }
+
+    #[test]
+    fn disconnect_reasons_are_classified() {
+        let reason = |text: NetworkText| DisconnectReason::from_network_text(&text);
+
+        assert_eq!(reason(NetworkText::key("LegacyMultiplayer.3")), DisconnectReason::Banned);
+        assert_eq!(reason(NetworkText::key("LegacyMultiplayer.4")), DisconnectReason::VersionMismatch);
+        assert_eq!(reason(NetworkText::key("CLI.ServerIsFull")), DisconnectReason::ServerFull);
+        assert_eq!(reason(NetworkText::literal("You are banned: griefing")), DisconnectReason::Banned);
+        assert_eq!(reason(NetworkText::literal("Server is full")), DisconnectReason::ServerFull);
+        assert_eq!(reason(NetworkText::literal("Wrong version")), DisconnectReason::VersionMismatch);
+        assert_eq!(reason(NetworkText::literal("Whitelist only")), DisconnectReason::Other("Whitelist only".into()));
+    }
+
+    #[test]
+    fn address_family_filters_and_orders_addresses() {
+        let v4 = |last: u8| SocketAddr::from((Ipv4Addr::new(192, 0, 2, last), 7777));
+        let v6 = |last: u16| SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last), 7777));
+        let resolved = [v6(1), v4(1), v6(2), v4(2)];
+        let order = |family: AddressFamily| family.order(resolved.into_iter());
+
+        assert_eq!(order(AddressFamily::Any), resolved);
+        assert_eq!(order(AddressFamily::PreferIpv4), [v4(1), v4(2), v6(1), v6(2)]);
+        assert_eq!(order(AddressFamily::PreferIpv6), [v6(1), v6(2), v4(1), v4(2)]);
+        assert_eq!(order(AddressFamily::Ipv4Only), [v4(1), v4(2)]);
+        assert_eq!(order(AddressFamily::Ipv6Only), [v6(1), v6(2)]);
+    }
+
+    #[tokio::test]
+    async fn handshake_replies_are_classified() {
+        let slot = SetUserSlot { player_slot: 3, check_bytes_in_client_loop: Some(false) };
+        let accepted = check(Some(slot.encode())).await;
+        assert!(accepted.is_online);
+        assert_eq!(accepted.outcome, ProbeOutcome::Answered);
+        assert_eq!(accepted.handshake, Some(HandshakeOutcome::Accepted { player_slot: 3 }));
+
+        let password = check(Some(RequestPassword.encode())).await;
+        assert!(password.is_online);
+        assert_eq!(password.handshake, Some(HandshakeOutcome::PasswordRequired));
+
+        let full = check(Some(disconnect(NetworkText::key("CLI.ServerIsFull")))).await;
+        assert!(full.is_online);
+        assert_eq!(full.handshake, Some(HandshakeOutcome::Rejected(DisconnectReason::ServerFull)));
+
+        let unexpected = check(Some(RequestWorldData.encode())).await;
+        assert!(unexpected.is_online);
+        assert!(matches!(unexpected.outcome, ProbeOutcome::ProtocolError(_)));
+    }
+
+    #[tokio::test]
+    async fn handshake_reports_the_server_version() {
+        let slot = SetUserSlot { player_slot: 0, check_bytes_in_client_loop: Some(false) };
+        let accepted = check(Some(slot.encode())).await;
+        assert_eq!(accepted.server_version, Some(ServerVersion::from_release(TERRARIA_RELEASE)));
+
+        // TShock names its version in the mismatch reply
+        let hint = disconnect(NetworkText::literal("Version mismatch, this server runs v1.4.4.8"));
+        let rejected = check(Some(hint)).await;
+        assert_eq!(rejected.handshake, Some(HandshakeOutcome::Rejected(DisconnectReason::VersionMismatch)));
+        assert_eq!(
+            rejected.server_version,
+            Some(ServerVersion { release: Some(277), version: Some("1.4.4.8".to_string()) })
+        );
+
+        let vanilla = check(Some(disconnect(NetworkText::key("LegacyMultiplayer.4")))).await;
+        assert_eq!(vanilla.server_version, None);
+    }
+
+    #[tokio::test]
+    async fn discovery_tries_releases_until_one_is_accepted() {
+        let options = CheckOptions::default();
+
+        let known = serve_release(270).await;
+        let found = discover_terraria_version("127.0.0.1", known, &options).await.unwrap();
+        assert_eq!(found, Some(ServerVersion { release: Some(270), version: Some("1.4.4.1".to_string()) }));
+
+        // Newer than the table: found by scanning ahead, without a version name
+        let newer = serve_release(TERRARIA_RELEASE + 5).await;
+        let found = discover_terraria_version("127.0.0.1", newer, &options).await.unwrap();
+        assert_eq!(found, Some(ServerVersion { release: Some(TERRARIA_RELEASE + 5), version: None }));
+
+        let beyond = serve_release(TERRARIA_RELEASE + RELEASE_SCAN_AHEAD + 1).await;
+        assert_eq!(discover_terraria_version("127.0.0.1", beyond, &options).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn failures_are_reported_by_outcome() {
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let refused = check_terraria_status("127.0.0.1", closed, &CheckOptions::default()).await;
+        assert!(!refused.is_online);
+        assert_eq!(refused.outcome, ProbeOutcome::Refused);
+        assert_eq!(refused.handshake, None);
+
+        // Accepts the connection but never answers
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = silent.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (_socket, _) = silent.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let options = CheckOptions { deadline: Duration::from_millis(200), ..Default::default() };
+        let timed_out = check_terraria_status("127.0.0.1", port, &options).await;
+        assert!(!timed_out.is_online);
+        assert_eq!(timed_out.outcome, ProbeOutcome::Timeout);
+        assert_eq!(timed_out.handshake, Some(HandshakeOutcome::NoReply));
+
+        let reset = check(None).await;
+        assert!(!reset.is_online);
+        assert_eq!(reset.outcome, ProbeOutcome::Reset);
+        assert_eq!(reset.handshake, Some(HandshakeOutcome::NoReply));
+
+        let unresolved = check_terraria_status("nonexistent.invalid", 7777, &CheckOptions::default()).await;
+        assert!(!unresolved.is_online);
+        assert!(matches!(unresolved.outcome, ProbeOutcome::DnsFailure(_)));
+    }
+
+    #[tokio::test]
+    async fn every_address_gets_its_own_result() {
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let slot = SetUserSlot { player_slot: 1, check_bytes_in_client_loop: Some(false) };
+        let live = SocketAddr::from((Ipv4Addr::LOCALHOST, serve_once(Some(slot.encode())).await));
+        let options = CheckOptions { probe_all_addresses: true, ..Default::default() };
+
+        let info = probe_each_address(&[closed, live], &options, Instant::now() + options.deadline).await;
+
+        // The first address that answered speaks for the server
+        assert!(info.is_online);
+        assert_eq!(info.handshake, Some(HandshakeOutcome::Accepted { player_slot: 1 }));
+        assert_eq!(info.addresses.len(), 2);
+        assert_eq!(info.addresses[0].address, closed);
+        assert!(!info.addresses[0].is_online);
+        assert_eq!(info.addresses[0].outcome, ProbeOutcome::Refused);
+        assert_eq!(info.addresses[1].address, live);
+        assert!(info.addresses[1].is_online);
+        assert_eq!(info.addresses[1].outcome, ProbeOutcome::Answered);
+        assert_eq!(info.addresses[1].family(), "IPv4");
+    }
 }
@@ -1,6 +1,10 @@
 use clap::Parser;
+use std::path::PathBuf;
 use std::{thread, time::Duration};
-use terraria_health_checker::check_server_status;
+use terraria_health_checker::{
+    check_server_status_deep, check_server_status_with_release, check_terraria_status, check_tshock_status,
+    discover_server_version, Capture, CheckOptions, ProbeOutcome, TShockConfig, DEFAULT_REST_PORT, TERRARIA_RELEASE,
+};
 use chrono::Local;
 
 #[derive(Parser, Debug)]
@@ -17,6 +21,34 @@ struct Args {
     /// Seconds between checks
     #[arg(short, long, default_value_t = 10)]
     interval: u64,
+
+    /// Release number to announce in the handshake (279 = 1.4.4.9)
+    #[arg(short, long, default_value_t = TERRARIA_RELEASE)]
+    release: u32,
+
+    /// Discover the server's game version before monitoring, and use it for the handshake
+    #[arg(short, long)]
+    discover: bool,
+
+    /// Log in as a guest after the handshake and report world details
+    #[arg(long)]
+    deep: bool,
+
+    /// Check every address the host resolves to (IPv4 and IPv6) and report each
+    #[arg(short, long)]
+    all_addresses: bool,
+
+    /// TShock REST API token; when set, player list and uptime are read from the REST API
+    #[arg(long)]
+    tshock_token: Option<String>,
+
+    /// Port of the TShock REST API
+    #[arg(long, default_value_t = DEFAULT_REST_PORT)]
+    tshock_port: u16,
+
+    /// Write every connection attempt and the bytes exchanged to this pcap file
+    #[arg(long)]
+    pcap: Option<PathBuf>,
 }
 
 fn main() {
@@ -24,20 +56,103 @@ fn main() {
 
     println!("Checking Terraria server at {}:{}...", args.host, args.port);
     println!("Interval: {}s", args.interval);
+    let capture = args.pcap.as_ref().map(|path| {
+        Capture::create(path).unwrap_or_else(|e| {
+            eprintln!("Cannot create capture file {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+    if let Some(capture) = &capture {
+        println!("Capture: {}", capture.path().display());
+    }
     println!("-----------------------------------------------------");
 
     if args.host == "127.0.0.1" && args.port == 7777 {
         println!("(Using defaults. To specify a server, run with: --host <IP> --port <PORT>)");
     }
 
+    let mut release = args.release;
+    if args.discover {
+        match discover_server_version(&args.host, args.port) {
+            Ok(Some(version)) => {
+                println!("Server version: {}", version);
+                release = version.release.unwrap_or(release);
+            }
+            Ok(None) => println!("Server version: could not be determined"),
+            Err(e) => println!("Server version: discovery failed ({})", e),
+        }
+        println!("-----------------------------------------------------");
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start the async runtime");
+    let tshock = args.tshock_token.map(|token| TShockConfig { rest_port: args.tshock_port, token });
+    let options = CheckOptions {
+        release,
+        deep: args.deep,
+        probe_all_addresses: args.all_addresses,
+        capture,
+        ..Default::default()
+    };
+
     loop {
         let timestamp = Local::now().format("%H:%M:%S");
-        match check_server_status(&args.host, args.port) {
+        let result = if let Some(config) = &tshock {
+            Ok(runtime.block_on(check_tshock_status(&args.host, args.port, &options, config)))
+        } else if args.all_addresses || options.capture.is_some() {
+            Ok(runtime.block_on(check_terraria_status(&args.host, args.port, &options)))
+        } else if args.deep {
+            check_server_status_deep(&args.host, args.port, release)
+        } else {
+            check_server_status_with_release(&args.host, args.port, release)
+        };
+        match result {
             Ok(info) => {
-                if info.is_online {
-                    println!("[{}] 🟢 ONLINE | Ping: {}ms", timestamp, info.latency_ms);
-                } else {
-                    println!("[{}] 🔴 OFFLINE | Connection timed out or refused", timestamp);
+                match (info.is_online, info.handshake) {
+                    (true, Some(handshake)) => {
+                        println!("[{}] 🟢 ONLINE | Ping: {}ms | {}", timestamp, info.latency_ms, handshake);
+                        if let Some(world) = info.world {
+                            let events: Vec<String> = world.events.iter().map(|e| e.to_string()).collect();
+                            println!(
+                                "           🌍 {} | {} | {} {} | {}{} | Events: {}",
+                                world.name,
+                                world.size(),
+                                world.clock(),
+                                if world.is_day { "☀" } else { "🌙" },
+                                world.difficulty,
+                                if world.hardmode == Some(true) { " (Hardmode)" } else { "" },
+                                if events.is_empty() { "none".to_string() } else { events.join(", ") },
+                            );
+                        }
+                        if let Some(status) = info.tshock {
+                            println!(
+                                "           👥 {}/{} players{} | Uptime: {}",
+                                status.player_count,
+                                status.max_players,
+                                if status.players.is_empty() { String::new() } else { format!(" ({})", status.players.join(", ")) },
+                                status.uptime,
+                            );
+                        } else if tshock.is_some() {
+                            println!("           👥 TShock REST unavailable, showing the game port check only");
+                        }
+                    }
+                    (false, Some(handshake)) => {
+                        println!("[{}] 🔴 OFFLINE | {} ({})", timestamp, handshake, info.outcome);
+                    }
+                    (_, None) => {
+                        println!("[{}] 🔴 OFFLINE | {}", timestamp, info.outcome);
+                    }
+                }
+                for address in &info.addresses {
+                    match &address.outcome {
+                        ProbeOutcome::Answered => println!(
+                            "           🟢 {} {} | Ping: {}ms",
+                            address.family(), address.address, address.latency_ms
+                        ),
+                        outcome => println!("           🔴 {} {} | {}", address.family(), address.address, outcome),
+                    }
                 }
             }
             Err(e) => {
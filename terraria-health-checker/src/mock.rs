@@ -0,0 +1,245 @@
+//! A scriptable fake Terraria server on a local TCP port, so the checker and
+//! the tools built on it can be tested without Mono or a real server binary.
+//! Enabled by the `mock` feature.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use terraria_health_checker::mock::{MockReply, MockResponder};
+//! use terraria_health_checker::protocol::NetworkText;
+//!
+//! // Accepts the first connection, kicks the second, and so on
+//! let server = MockResponder::new(MockReply::Accept(0))
+//!     .then(MockReply::Disconnect(NetworkText::key("CLI.ServerIsFull")))
+//!     .start()
+//!     .await?;
+//! let options = terraria_health_checker::CheckOptions::default();
+//! let info = terraria_health_checker::check_terraria_status("127.0.0.1", server.port(), &options).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::protocol::{self, ConnectRequest, Disconnect, Message, NetworkText, RequestPassword, SetUserSlot};
+use crate::WorldInfo;
+
+/// Message type and payload of every message received, shared with the connections.
+type Received = Arc<Mutex<Vec<(u8, Vec<u8>)>>>;
+
+/// How the server answers a ConnectRequest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockReply {
+    /// SetUserSlot with this slot, then the connection is held open.
+    Accept(u8),
+    /// SetUserSlot with slot 0, then this WorldInfo once the client asks for it.
+    World(WorldInfo),
+    /// RequestPassword, then the connection is held open.
+    PasswordRequired,
+    /// Disconnect with this reason, then the connection is closed.
+    Disconnect(NetworkText),
+    /// No reply; the connection is held open, like a hung server.
+    Stall,
+    /// The connection is closed without a reply.
+    Close,
+}
+
+/// Builder for a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockResponder {
+    script: Vec<MockReply>,
+    delay: Duration,
+    release: Option<u32>,
+}
+
+impl MockResponder {
+    /// A server answering every connection with `reply`.
+    pub fn new(reply: MockReply) -> Self {
+        Self { script: vec![reply], delay: Duration::ZERO, release: None }
+    }
+
+    /// Adds `reply` to the script. Connections are answered in script order,
+    /// starting over after the last entry.
+    pub fn then(mut self, reply: MockReply) -> Self {
+        self.script.push(reply);
+        self
+    }
+
+    /// Holds every reply back for `delay`.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Only lets `release` through, like a vanilla server: any other release
+    /// is kicked with the version mismatch key, without advancing the script.
+    pub fn release(mut self, release: u32) -> Self {
+        self.release = Some(release);
+        self
+    }
+
+    /// Binds a TCP port on localhost and starts serving on it.
+    pub async fn start(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (refusing, mut refusing_changes) = watch::channel(false);
+
+        let responder = Arc::new(self);
+        let answered = Arc::new(AtomicUsize::new(0));
+        let seen = received.clone();
+        let task = tokio::spawn(async move {
+            let mut listener = Some(listener);
+            loop {
+                let Some(open) = &listener else {
+                    // Nothing listens until connections are allowed again, so connects are refused
+                    if refusing_changes.changed().await.is_err() {
+                        return;
+                    }
+                    if !*refusing_changes.borrow() {
+                        listener = TcpListener::bind(addr).await.ok();
+                    }
+                    continue;
+                };
+
+                let close = tokio::select! {
+                    accepted = open.accept() => {
+                        if let Ok((socket, _)) = accepted {
+                            tokio::spawn(serve(socket, responder.clone(), answered.clone(), seen.clone()));
+                        }
+                        false
+                    }
+                    changed = refusing_changes.changed() => match changed {
+                        Ok(()) => *refusing_changes.borrow(),
+                        Err(_) => return,
+                    },
+                };
+                if close {
+                    listener = None;
+                }
+            }
+        });
+
+        Ok(MockServer { addr, received, refusing, task })
+    }
+}
+
+/// A running fake server. Stops listening when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    received: Received,
+    refusing: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Message type and payload of every message clients sent so far, in
+    /// arrival order across connections.
+    pub fn received(&self) -> Vec<(u8, Vec<u8>)> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Releases announced in the ConnectRequests received so far.
+    pub fn connect_releases(&self) -> Vec<Option<u32>> {
+        self.received()
+            .iter()
+            .filter(|(msg_type, _)| *msg_type == protocol::CONNECT_REQUEST)
+            .map(|(_, payload)| ConnectRequest::decode(payload).ok().and_then(|request| request.release()))
+            .collect()
+    }
+
+    /// Stops listening, so new connections are refused as if the server were
+    /// down, or starts listening again on the same port.
+    pub fn refuse_connections(&self, refuse: bool) {
+        self.refusing.send_replace(refuse);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answers one connection according to the script.
+async fn serve(
+    mut socket: TcpStream,
+    responder: Arc<MockResponder>,
+    answered: Arc<AtomicUsize>,
+    received: Received,
+) {
+    let record = |msg_type: u8, payload: &[u8]| received.lock().unwrap().push((msg_type, payload.to_vec()));
+
+    let Ok((msg_type, payload)) = protocol::read_packet_async(&mut socket).await else { return };
+    record(msg_type, &payload);
+    if msg_type != protocol::CONNECT_REQUEST {
+        return;
+    }
+
+    let release = ConnectRequest::decode(&payload).ok().and_then(|request| request.release());
+    if let Some(expected) = responder.release
+        && release != Some(expected)
+    {
+        let mismatch = Disconnect { reason: NetworkText::key("LegacyMultiplayer.4") };
+        let _ = socket.write_all(&mismatch.encode()).await;
+        return;
+    }
+
+    let reply = &responder.script[answered.fetch_add(1, Ordering::SeqCst) % responder.script.len()];
+    tokio::time::sleep(responder.delay).await;
+    let world = match reply {
+        MockReply::Accept(player_slot) => {
+            let slot = SetUserSlot { player_slot: *player_slot, check_bytes_in_client_loop: Some(false) };
+            if socket.write_all(&slot.encode()).await.is_err() {
+                return;
+            }
+            None
+        }
+        MockReply::World(world) => {
+            let slot = SetUserSlot { player_slot: 0, check_bytes_in_client_loop: Some(false) };
+            if socket.write_all(&slot.encode()).await.is_err() {
+                return;
+            }
+            Some(world)
+        }
+        MockReply::PasswordRequired => {
+            if socket.write_all(&RequestPassword.encode()).await.is_err() {
+                return;
+            }
+            None
+        }
+        MockReply::Disconnect(reason) => {
+            let _ = socket.write_all(&Disconnect { reason: reason.clone() }.encode()).await;
+            return;
+        }
+        MockReply::Stall => None,
+        MockReply::Close => return,
+    };
+
+    // Hold the connection until the client goes away, answering a world request if scripted
+    while let Ok((msg_type, payload)) = protocol::read_packet_async(&mut socket).await {
+        record(msg_type, &payload);
+        if msg_type == protocol::REQUEST_WORLD_DATA
+            && let Some(world) = world
+            && socket.write_all(&world.encode()).await.is_err()
+        {
+            return;
+        }
+    }
+}
@@ -0,0 +1,682 @@
+//! Terraria network message codec.
+//!
+//! Every message is framed as a little-endian `u16` total length (header
+//! included), a `u8` message type and the payload. Payloads use .NET
+//! `BinaryWriter` encoding: little-endian integers and strings prefixed with
+//! a 7-bit encoded byte length.
+
+use std::fmt;
+use std::io::{self, Read};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Length prefix plus message type.
+pub const HEADER_LEN: usize = 3;
+/// Largest payload a frame can carry: its `u16` length counts the header too.
+pub const MAX_PAYLOAD_LEN: usize = u16::MAX as usize - HEADER_LEN;
+
+/// Message type sent by clients to start a session (`"Terraria<release>"`).
+pub const CONNECT_REQUEST: u8 = 1;
+/// Message type used by the server to kick a client, carrying a NetworkText reason.
+pub const DISCONNECT: u8 = 2;
+/// Message type used by the server to assign the client a player slot.
+pub const SET_USER_SLOT: u8 = 3;
+/// Message type carrying the client's character (name, looks, difficulty).
+pub const PLAYER_INFO: u8 = 4;
+/// Message type used by clients to ask for WorldInfo.
+pub const REQUEST_WORLD_DATA: u8 = 6;
+/// Message type carrying the world name, size, time and flags.
+pub const WORLD_INFO: u8 = 7;
+/// Message type used by the server to ask for the server password.
+pub const REQUEST_PASSWORD: u8 = 37;
+/// Message type carrying the client's answer to RequestPassword.
+pub const SEND_PASSWORD: u8 = 38;
+/// Message type carrying the client's UUID.
+pub const CLIENT_UUID: u8 = 68;
+
+/// A typed Terraria message that can be framed and parsed.
+pub trait Message: Sized {
+    const TYPE: u8;
+
+    fn write_payload(&self, writer: &mut PacketWriter);
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self>;
+
+    /// Encodes the message as a complete frame, header included.
+    ///
+    /// # Panics
+    /// If the payload is longer than [`MAX_PAYLOAD_LEN`].
+    fn encode(&self) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        self.write_payload(&mut writer);
+        encode_packet(Self::TYPE, writer.as_bytes())
+    }
+
+    /// Parses a message payload (the bytes after the header).
+    fn decode(payload: &[u8]) -> io::Result<Self> {
+        Self::read_payload(&mut PayloadReader::new(payload))
+    }
+}
+
+/// Frames a message: u16 little-endian total length (header included), u8 type, payload.
+///
+/// # Panics
+/// If `payload` is longer than [`MAX_PAYLOAD_LEN`], which no frame can describe.
+pub fn encode_packet(msg_type: u8, payload: &[u8]) -> Vec<u8> {
+    let total_len = u16::try_from(payload.len() + HEADER_LEN).unwrap_or_else(|_| {
+        panic!("{} byte payload is over the {} bytes a Terraria frame can carry", payload.len(), MAX_PAYLOAD_LEN)
+    });
+    let mut packet = Vec::with_capacity(total_len as usize);
+    packet.extend_from_slice(&total_len.to_le_bytes());
+    packet.push(msg_type);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Parses the header of a frame, returning the total frame length and message type.
+pub fn decode_header(header: [u8; HEADER_LEN]) -> io::Result<(usize, u8)> {
+    let total_len = u16::from_le_bytes([header[0], header[1]]) as usize;
+    if total_len < HEADER_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Packet length shorter than header"));
+    }
+    Ok((total_len, header[2]))
+}
+
+/// Splits one complete frame off the front of `buf`.
+///
+/// Returns the message type, its payload and the number of bytes consumed, or
+/// `Ok(None)` if `buf` does not hold a whole frame yet.
+pub fn decode_packet(buf: &[u8]) -> io::Result<Option<(u8, &[u8], usize)>> {
+    let Some(header) = buf.get(..HEADER_LEN) else {
+        return Ok(None);
+    };
+    let (total_len, msg_type) = decode_header([header[0], header[1], header[2]])?;
+    Ok(buf
+        .get(HEADER_LEN..total_len)
+        .map(|payload| (msg_type, payload, total_len)))
+}
+
+/// Reads one framed message, returning its type and payload.
+pub fn read_packet<R: Read>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    let (total_len, msg_type) = decode_header(header)?;
+
+    let mut payload = vec![0u8; total_len - HEADER_LEN];
+    reader.read_exact(&mut payload)?;
+    Ok((msg_type, payload))
+}
+
+/// Async version of [`read_packet`].
+pub async fn read_packet_async<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    let (total_len, msg_type) = decode_header(header)?;
+
+    let mut payload = vec![0u8; total_len - HEADER_LEN];
+    reader.read_exact(&mut payload).await?;
+    Ok((msg_type, payload))
+}
+
+/// Builder for a message payload.
+#[derive(Debug, Default)]
+pub struct PacketWriter {
+    buf: Vec<u8>,
+}
+
+impl PacketWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Writes a .NET `BinaryWriter` string: 7-bit encoded byte length followed by UTF-8.
+    pub fn write_string(&mut self, value: &str) {
+        let mut len = value.len() as u32;
+        while len >= 0x80 {
+            self.buf.push((len as u8) | 0x80);
+            len >>= 7;
+        }
+        self.buf.push(len as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn write_network_text(&mut self, text: &NetworkText) {
+        self.write_u8(text.mode as u8);
+        self.write_string(&text.text);
+        if text.mode != NetworkTextMode::Literal {
+            self.write_u8(text.substitutions.len() as u8);
+            for substitution in &text.substitutions {
+                self.write_network_text(substitution);
+            }
+        }
+    }
+
+    pub fn write_color(&mut self, color: Color) {
+        self.buf.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+}
+
+/// Cursor over a message payload.
+pub struct PayloadReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Bytes not read yet.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        let byte = *self.data.get(self.pos).ok_or_else(truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub fn read_bool(&mut self) -> io::Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_i16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn skip(&mut self, count: usize) -> io::Result<()> {
+        self.read_bytes(count).map(|_| ())
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + count).ok_or_else(truncated)?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn read_string(&mut self) -> io::Result<String> {
+        let mut len: u32 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            len |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "String length prefix too long"));
+            }
+        }
+
+        let bytes = self.read_bytes(len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn read_network_text(&mut self) -> io::Result<NetworkText> {
+        let mode = NetworkTextMode::try_from(self.read_u8()?)?;
+        let text = self.read_string()?;
+        let mut substitutions = Vec::new();
+        if mode != NetworkTextMode::Literal {
+            let count = self.read_u8()?;
+            for _ in 0..count {
+                substitutions.push(self.read_network_text()?);
+            }
+        }
+        Ok(NetworkText { mode, text, substitutions })
+    }
+
+    pub fn read_color(&mut self) -> io::Result<Color> {
+        let [r, g, b] = self.read_array()?;
+        Ok(Color { r, g, b })
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Payload truncated")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkTextMode {
+    Literal = 0,
+    Formattable = 1,
+    LocalizationKey = 2,
+}
+
+impl TryFrom<u8> for NetworkTextMode {
+    type Error = io::Error;
+
+    fn try_from(mode: u8) -> io::Result<Self> {
+        match mode {
+            0 => Ok(NetworkTextMode::Literal),
+            1 => Ok(NetworkTextMode::Formattable),
+            2 => Ok(NetworkTextMode::LocalizationKey),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown NetworkText mode {}", other),
+            )),
+        }
+    }
+}
+
+/// Server-sent text: literal, a format string, or a localization key, with substitutions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkText {
+    pub mode: NetworkTextMode,
+    pub text: String,
+    pub substitutions: Vec<NetworkText>,
+}
+
+impl NetworkText {
+    pub fn literal(text: impl Into<String>) -> Self {
+        Self { mode: NetworkTextMode::Literal, text: text.into(), substitutions: Vec::new() }
+    }
+
+    pub fn key(key: impl Into<String>) -> Self {
+        Self { mode: NetworkTextMode::LocalizationKey, text: key.into(), substitutions: Vec::new() }
+    }
+}
+
+impl fmt::Display for NetworkText {
+    /// Shows the literal text or key; we have no localization tables to resolve keys.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)?;
+        if !self.substitutions.is_empty() {
+            let substitutions: Vec<String> = self.substitutions.iter().map(|s| s.to_string()).collect();
+            write!(f, " ({})", substitutions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Message 1: the first thing a client sends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectRequest {
+    /// `"Terraria"` followed by the release number, e.g. `Terraria279`.
+    pub version: String,
+}
+
+impl ConnectRequest {
+    pub fn for_release(release: u32) -> Self {
+        Self { version: format!("Terraria{}", release) }
+    }
+
+    /// The release number announced, if the version string is well formed.
+    pub fn release(&self) -> Option<u32> {
+        self.version.strip_prefix("Terraria")?.parse().ok()
+    }
+}
+
+impl Message for ConnectRequest {
+    const TYPE: u8 = CONNECT_REQUEST;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_string(&self.version);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self { version: reader.read_string()? })
+    }
+}
+
+/// Message 2: the server kicks the client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disconnect {
+    pub reason: NetworkText,
+}
+
+impl Message for Disconnect {
+    const TYPE: u8 = DISCONNECT;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_network_text(&self.reason);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self { reason: reader.read_network_text()? })
+    }
+}
+
+/// Message 3: the server accepted the client into a player slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetUserSlot {
+    pub player_slot: u8,
+    /// Only sent by 1.4.4 and later servers.
+    pub check_bytes_in_client_loop: Option<bool>,
+}
+
+impl Message for SetUserSlot {
+    const TYPE: u8 = SET_USER_SLOT;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_u8(self.player_slot);
+        if let Some(flag) = self.check_bytes_in_client_loop {
+            writer.write_bool(flag);
+        }
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        let player_slot = reader.read_u8()?;
+        let check_bytes_in_client_loop = if reader.remaining() > 0 { Some(reader.read_bool()?) } else { None };
+        Ok(Self { player_slot, check_bytes_in_client_loop })
+    }
+}
+
+/// Message 4: the client's character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfo {
+    pub player_slot: u8,
+    pub skin_variant: u8,
+    pub hair: u8,
+    pub name: String,
+    pub hair_dye: u8,
+    pub hide_accessories: u16,
+    pub hide_misc: u8,
+    /// Hair, skin, eye, shirt, undershirt, pants and shoe colors.
+    pub colors: [Color; 7],
+    /// Bit flags: mediumcore, hardcore, extra accessory, journey mode.
+    pub difficulty_flags: u8,
+    pub torch_flags: u8,
+    /// Consumed permanent buffs (1.4.4 and later).
+    pub buff_flags: u8,
+}
+
+impl PlayerInfo {
+    /// A plain classic character, as sent by the deep probe.
+    pub fn guest(player_slot: u8, name: impl Into<String>) -> Self {
+        Self {
+            player_slot,
+            skin_variant: 0,
+            hair: 0,
+            name: name.into(),
+            hair_dye: 0,
+            hide_accessories: 0,
+            hide_misc: 0,
+            colors: [Color { r: 0x80, g: 0x80, b: 0x80 }; 7],
+            difficulty_flags: 0,
+            torch_flags: 0,
+            buff_flags: 0,
+        }
+    }
+}
+
+impl Message for PlayerInfo {
+    const TYPE: u8 = PLAYER_INFO;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_u8(self.player_slot);
+        writer.write_u8(self.skin_variant);
+        writer.write_u8(self.hair);
+        writer.write_string(&self.name);
+        writer.write_u8(self.hair_dye);
+        writer.write_bytes(&self.hide_accessories.to_le_bytes());
+        writer.write_u8(self.hide_misc);
+        for color in self.colors {
+            writer.write_color(color);
+        }
+        writer.write_u8(self.difficulty_flags);
+        writer.write_u8(self.torch_flags);
+        writer.write_u8(self.buff_flags);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        let player_slot = reader.read_u8()?;
+        let skin_variant = reader.read_u8()?;
+        let hair = reader.read_u8()?;
+        let name = reader.read_string()?;
+        let hair_dye = reader.read_u8()?;
+        let hide_accessories = u16::from_le_bytes(reader.read_array()?);
+        let hide_misc = reader.read_u8()?;
+        let mut colors = [Color::default(); 7];
+        for color in &mut colors {
+            *color = reader.read_color()?;
+        }
+        let difficulty_flags = reader.read_u8()?;
+        let torch_flags = reader.read_u8()?;
+        let buff_flags = if reader.remaining() > 0 { reader.read_u8()? } else { 0 };
+        Ok(Self {
+            player_slot,
+            skin_variant,
+            hair,
+            name,
+            hair_dye,
+            hide_accessories,
+            hide_misc,
+            colors,
+            difficulty_flags,
+            torch_flags,
+            buff_flags,
+        })
+    }
+}
+
+/// Message 6: the client asks for WorldInfo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestWorldData;
+
+impl Message for RequestWorldData {
+    const TYPE: u8 = REQUEST_WORLD_DATA;
+
+    fn write_payload(&self, _writer: &mut PacketWriter) {}
+
+    fn read_payload(_reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self)
+    }
+}
+
+/// Message 37: the server wants a password before going on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestPassword;
+
+impl Message for RequestPassword {
+    const TYPE: u8 = REQUEST_PASSWORD;
+
+    fn write_payload(&self, _writer: &mut PacketWriter) {}
+
+    fn read_payload(_reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self)
+    }
+}
+
+/// Message 38: the client's answer to RequestPassword.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendPassword {
+    pub password: String,
+}
+
+impl Message for SendPassword {
+    const TYPE: u8 = SEND_PASSWORD;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_string(&self.password);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self { password: reader.read_string()? })
+    }
+}
+
+/// Message 68: the client's UUID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientUuid {
+    pub uuid: String,
+}
+
+impl Message for ClientUuid {
+    const TYPE: u8 = CLIENT_UUID;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        writer.write_string(&self.uuid);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        Ok(Self { uuid: reader.read_string()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<M: Message + PartialEq + fmt::Debug>(message: M) {
+        let frame = message.encode();
+        let (msg_type, payload, consumed) = decode_packet(&frame).unwrap().unwrap();
+        assert_eq!(msg_type, M::TYPE);
+        assert_eq!(consumed, frame.len());
+        assert_eq!(M::decode(payload).unwrap(), message);
+    }
+
+    #[test]
+    fn frames_length_type_and_payload() {
+        let frame = ConnectRequest::for_release(279).encode();
+        assert_eq!(frame, b"\x0f\x00\x01\x0bTerraria279");
+    }
+
+    #[test]
+    fn largest_payload_fills_the_length_prefix() {
+        let frame = encode_packet(CONNECT_REQUEST, &vec![0; MAX_PAYLOAD_LEN]);
+        assert_eq!(&frame[..2], &u16::MAX.to_le_bytes());
+        assert_eq!(decode_packet(&frame).unwrap().unwrap().2, frame.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "over the 65532 bytes")]
+    fn oversized_payload_is_not_framed() {
+        encode_packet(CONNECT_REQUEST, &vec![0; MAX_PAYLOAD_LEN + 1]);
+    }
+
+    #[test]
+    fn decode_packet_waits_for_a_whole_frame() {
+        let frame = ConnectRequest::for_release(279).encode();
+        assert!(decode_packet(&frame[..2]).unwrap().is_none());
+        assert!(decode_packet(&frame[..frame.len() - 1]).unwrap().is_none());
+        assert!(decode_packet(&[0x02, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn read_packet_reads_one_frame_from_a_stream() {
+        let mut stream = SetUserSlot { player_slot: 4, check_bytes_in_client_loop: None }.encode();
+        stream.extend(RequestPassword.encode());
+        let mut reader = stream.as_slice();
+
+        assert_eq!(read_packet(&mut reader).unwrap(), (SET_USER_SLOT, vec![4]));
+        assert_eq!(read_packet(&mut reader).unwrap(), (REQUEST_PASSWORD, vec![]));
+    }
+
+    #[test]
+    fn strings_use_seven_bit_length_prefix() {
+        let long = "x".repeat(300);
+        let mut writer = PacketWriter::new();
+        writer.write_string(&long);
+        assert_eq!(&writer.as_bytes()[..2], &[0xAC, 0x02]);
+        assert_eq!(PayloadReader::new(writer.as_bytes()).read_string().unwrap(), long);
+
+        let mut writer = PacketWriter::new();
+        writer.write_string("Bem-vindo ao Bananil ☀");
+        assert_eq!(PayloadReader::new(writer.as_bytes()).read_string().unwrap(), "Bem-vindo ao Bananil ☀");
+    }
+
+    #[test]
+    fn network_text_round_trips_with_substitutions() {
+        let text = NetworkText {
+            mode: NetworkTextMode::Formattable,
+            text: "{0} has joined.".to_string(),
+            substitutions: vec![NetworkText::literal("Bananil"), NetworkText::key("LegacyMultiplayer.4")],
+        };
+        let mut writer = PacketWriter::new();
+        writer.write_network_text(&text);
+        assert_eq!(PayloadReader::new(writer.as_bytes()).read_network_text().unwrap(), text);
+    }
+
+    #[test]
+    fn colors_are_three_bytes() {
+        let mut writer = PacketWriter::new();
+        writer.write_color(Color { r: 1, g: 2, b: 3 });
+        assert_eq!(writer.as_bytes(), &[1, 2, 3]);
+        assert_eq!(PayloadReader::new(&[1, 2, 3]).read_color().unwrap(), Color { r: 1, g: 2, b: 3 });
+    }
+
+    #[test]
+    fn truncated_payloads_are_errors() {
+        assert!(PayloadReader::new(&[0x05, b'a']).read_string().is_err());
+        assert!(PayloadReader::new(&[0x01, 0x00]).read_i32().is_err());
+        assert!(PayloadReader::new(&[0x07]).read_network_text().is_err());
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        round_trip(ConnectRequest::for_release(279));
+        round_trip(Disconnect { reason: NetworkText::key("LegacyMultiplayer.4") });
+        round_trip(SetUserSlot { player_slot: 3, check_bytes_in_client_loop: Some(false) });
+        round_trip(SetUserSlot { player_slot: 3, check_bytes_in_client_loop: None });
+        round_trip(PlayerInfo::guest(3, "HealthCheck"));
+        round_trip(RequestWorldData);
+        round_trip(RequestPassword);
+        round_trip(SendPassword { password: "banana".to_string() });
+        round_trip(ClientUuid { uuid: "00000000-0000-0000-0000-000000000000".to_string() });
+    }
+
+    #[test]
+    fn connect_request_exposes_release() {
+        assert_eq!(ConnectRequest::for_release(248).release(), Some(248));
+        assert_eq!(ConnectRequest { version: "Minecraft".to_string() }.release(), None);
+    }
+}
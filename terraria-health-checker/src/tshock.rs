@@ -0,0 +1,123 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Default port of the TShock REST API.
+pub const DEFAULT_REST_PORT: u16 = 7878;
+
+/// Where and how to reach a TShock server's REST API.
+#[derive(Debug, Clone)]
+pub struct TShockConfig {
+    pub rest_port: u16,
+    /// Application token from TShock's `ApplicationRestTokens` config.
+    pub token: String,
+}
+
+/// Server state reported by TShock's `/v2/server/status`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TShockStatus {
+    pub server_name: String,
+    pub world: String,
+    pub player_count: u32,
+    pub max_players: u32,
+    pub players: Vec<String>,
+    /// Uptime as reported by TShock (`d.hh:mm:ss`).
+    pub uptime: String,
+    pub server_version: Option<String>,
+    pub tshock_version: Option<String>,
+    pub password_protected: bool,
+}
+
+#[derive(Debug)]
+pub enum TShockError {
+    /// Nothing answered on the REST port, or it isn't an HTTP server.
+    Unavailable(reqwest::Error),
+    /// REST is up but rejected our token.
+    Unauthorized(String),
+    /// REST answered with an error status or a body we could not read.
+    Api(String),
+}
+
+impl fmt::Display for TShockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TShockError::Unavailable(e) => write!(f, "TShock REST unavailable: {}", e),
+            TShockError::Unauthorized(msg) => write!(f, "TShock REST rejected the token: {}", msg),
+            TShockError::Api(msg) => write!(f, "TShock REST error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TShockError {}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    status: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    world: String,
+    #[serde(default)]
+    playercount: u32,
+    #[serde(default)]
+    maxplayers: u32,
+    #[serde(default)]
+    uptime: String,
+    #[serde(default)]
+    serverversion: Option<String>,
+    #[serde(default)]
+    tshockversion: Option<String>,
+    #[serde(default)]
+    serverpassword: bool,
+    #[serde(default)]
+    players: Vec<PlayerEntry>,
+}
+
+#[derive(Deserialize)]
+struct PlayerEntry {
+    nickname: String,
+}
+
+/// Fetches `/v2/server/status` (with the player list) from a TShock REST API.
+pub async fn fetch_tshock_status(host: &str, config: &TShockConfig) -> Result<TShockStatus, TShockError> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .map_err(TShockError::Unavailable)?;
+
+    let url = format!("http://{}:{}/v2/server/status", host, config.rest_port);
+    let response = client
+        .get(&url)
+        .query(&[("players", "true"), ("token", config.token.as_str())])
+        .send()
+        .await
+        .map_err(TShockError::Unavailable)?;
+
+    let http_status = response.status();
+    let body: StatusResponse = response
+        .json()
+        .await
+        .map_err(|e| TShockError::Api(format!("HTTP {}: {}", http_status, e)))?;
+
+    // TShock repeats the HTTP status in the body, older versions only set it there
+    match body.status.as_str() {
+        "200" => {}
+        "401" | "403" => return Err(TShockError::Unauthorized(body.error.unwrap_or(body.status))),
+        _ => return Err(TShockError::Api(body.error.unwrap_or(body.status))),
+    }
+
+    Ok(TShockStatus {
+        server_name: body.name,
+        world: body.world,
+        player_count: body.playercount,
+        max_players: body.maxplayers,
+        players: body.players.into_iter().map(|p| p.nickname).collect(),
+        uptime: body.uptime,
+        server_version: body.serverversion,
+        tshock_version: body.tshockversion,
+        password_protected: body.serverpassword,
+    })
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// Release numbers sent in `ConnectRequest`, paired with the game version they ship in.
+/// Newest first, since that is what a running server most likely accepts.
+pub const KNOWN_RELEASES: &[(u32, &str)] = &[
+    (279, "1.4.4.9"),
+    (278, "1.4.4.8.1"),
+    (277, "1.4.4.8"),
+    (276, "1.4.4.7"),
+    (275, "1.4.4.6"),
+    (274, "1.4.4.5"),
+    (273, "1.4.4.4"),
+    (272, "1.4.4.3"),
+    (271, "1.4.4.2"),
+    (270, "1.4.4.1"),
+    (269, "1.4.4"),
+    (248, "1.4.3.6"),
+    (247, "1.4.3.5"),
+    (246, "1.4.3.4"),
+    (245, "1.4.3.3"),
+    (244, "1.4.3.2"),
+    (243, "1.4.3.1"),
+    (242, "1.4.3"),
+    (238, "1.4.2.3"),
+    (237, "1.4.2.2"),
+    (236, "1.4.2.1"),
+    (235, "1.4.2"),
+    (234, "1.4.1.2"),
+    (233, "1.4.1.1"),
+    (232, "1.4.1"),
+    (230, "1.4.0.5"),
+    (228, "1.4.0.4"),
+    (227, "1.4.0.3"),
+    (226, "1.4.0.2"),
+    (225, "1.4.0.1"),
+];
+
+/// How many release numbers above the newest known one discovery will try,
+/// so servers running a release newer than this table are still found.
+pub const RELEASE_SCAN_AHEAD: u32 = 60;
+
+/// The version a server accepts, as far as we could tell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerVersion {
+    /// Release number the server accepted in `ConnectRequest`, if known.
+    pub release: Option<u32>,
+    /// Human-readable game version (e.g. `1.4.4.9`), if known.
+    pub version: Option<String>,
+}
+
+impl ServerVersion {
+    pub fn from_release(release: u32) -> Self {
+        Self {
+            release: Some(release),
+            version: version_for_release(release).map(str::to_string),
+        }
+    }
+
+    pub fn from_version(version: String) -> Self {
+        Self {
+            release: release_for_version(&version),
+            version: Some(version),
+        }
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.version, self.release) {
+            (Some(version), Some(release)) => write!(f, "{} (release {})", version, release),
+            (Some(version), None) => write!(f, "{}", version),
+            (None, Some(release)) => write!(f, "unknown version (release {})", release),
+            (None, None) => write!(f, "unknown version"),
+        }
+    }
+}
+
+pub fn version_for_release(release: u32) -> Option<&'static str> {
+    KNOWN_RELEASES.iter().find(|(r, _)| *r == release).map(|(_, v)| *v)
+}
+
+pub fn release_for_version(version: &str) -> Option<u32> {
+    KNOWN_RELEASES.iter().find(|(_, v)| *v == version).map(|(r, _)| *r)
+}
+
+/// Release numbers to try during discovery: the known table, then the unknown ones above it.
+pub fn discovery_candidates() -> impl Iterator<Item = u32> {
+    let newest = KNOWN_RELEASES[0].0;
+    KNOWN_RELEASES
+        .iter()
+        .map(|(release, _)| *release)
+        .chain(newest + 1..=newest + RELEASE_SCAN_AHEAD)
+}
+
+/// Pulls a game version such as `1.4.4.9` or `v1.4.4.9` out of a disconnect message.
+/// Vanilla servers never include one, but TShock and some plugins do.
+pub fn parse_version_hint(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .map(|word| word.trim_start_matches(['v', 'V']).trim_end_matches(['.', '!', ':']))
+        .find(|word| {
+            word.starts_with("1.")
+                && word.split('.').count() >= 3
+                && word.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_map_to_versions() {
+        assert_eq!(version_for_release(279), Some("1.4.4.9"));
+        assert_eq!(version_for_release(278), Some("1.4.4.8.1"));
+        assert_eq!(version_for_release(250), None);
+        assert_eq!(release_for_version("1.4.3.6"), Some(248));
+        assert_eq!(release_for_version("1.3.5.3"), None);
+        assert_eq!(ServerVersion::from_release(400).to_string(), "unknown version (release 400)");
+    }
+
+    #[test]
+    fn known_releases_are_newest_first() {
+        assert!(KNOWN_RELEASES.windows(2).all(|pair| pair[0].0 > pair[1].0));
+    }
+
+    #[test]
+    fn discovery_tries_the_table_then_newer_releases() {
+        let candidates: Vec<u32> = discovery_candidates().collect();
+        let newest = KNOWN_RELEASES[0].0;
+
+        assert_eq!(candidates.len(), KNOWN_RELEASES.len() + RELEASE_SCAN_AHEAD as usize);
+        assert_eq!(&candidates[..2], &[newest, KNOWN_RELEASES[1].0]);
+        assert_eq!(candidates[KNOWN_RELEASES.len()], newest + 1);
+        assert_eq!(candidates.last(), Some(&(newest + RELEASE_SCAN_AHEAD)));
+    }
+
+    #[test]
+    fn version_hints_are_found_in_disconnect_text() {
+        assert_eq!(parse_version_hint("This server runs v1.4.4.9."), Some("1.4.4.9".to_string()));
+        assert_eq!(parse_version_hint("Wrong version (1.4.3.6), update your game"), Some("1.4.3.6".to_string()));
+        assert_eq!(parse_version_hint("Version: 1.4.4.8.1!"), Some("1.4.4.8.1".to_string()));
+        assert_eq!(parse_version_hint("You are not using the same version as this server."), None);
+        assert_eq!(parse_version_hint("Protocol 1.4 only"), None);
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+use std::io;
+
+use crate::protocol::{Message, PacketWriter, PayloadReader, WORLD_INFO};
+
+/// World details read from the server's WorldInfo message during a deep probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldInfo {
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    /// Ticks since dawn (4:30) during the day, or since dusk (19:30) at night.
+    pub time: i32,
+    pub is_day: bool,
+    pub moon_phase: u8,
+    pub difficulty: Difficulty,
+    /// `None` when the server's WorldInfo layout is not one we know past the basic fields.
+    pub hardmode: Option<bool>,
+    pub events: Vec<WorldEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldSize {
+    Small,
+    Medium,
+    Large,
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Classic,
+    Expert,
+    Master,
+    Journey,
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldEvent {
+    BloodMoon,
+    Eclipse,
+    PumpkinMoon,
+    FrostMoon,
+    SlimeRain,
+    Party,
+    Sandstorm,
+    OldOnesArmy,
+    LanternNight,
+    Rain,
+}
+
+impl WorldInfo {
+    pub fn size(&self) -> WorldSize {
+        match self.width {
+            4200 => WorldSize::Small,
+            6400 => WorldSize::Medium,
+            8400 => WorldSize::Large,
+            _ => WorldSize::Custom,
+        }
+    }
+
+    /// In-game clock time as `HH:MM`.
+    pub fn clock(&self) -> String {
+        // Day starts at 4:30, night at 19:30; 60 ticks per in-game minute
+        let start_minutes = if self.is_day { 4 * 60 + 30 } else { 19 * 60 + 30 };
+        let minutes = (start_minutes + self.time.max(0) / 60) % (24 * 60);
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+/// Message 7, in the 1.4.4 layout. Fields we don't model are skipped when
+/// reading and written as zero.
+impl Message for WorldInfo {
+    const TYPE: u8 = WORLD_INFO;
+
+    fn write_payload(&self, writer: &mut PacketWriter) {
+        let has = |event| self.events.contains(&event);
+        let bit = |on: bool, mask: u8| if on { mask } else { 0 };
+
+        writer.write_i32(self.time);
+        writer.write_u8(bit(self.is_day, 0x01) | bit(has(WorldEvent::BloodMoon), 0x02) | bit(has(WorldEvent::Eclipse), 0x04));
+        writer.write_u8(self.moon_phase);
+        writer.write_i16(self.width as i16);
+        writer.write_i16(self.height as i16);
+        writer.write_bytes(&[0; 2 + 2 + 2 + 2]);
+        writer.write_i32(0);
+        writer.write_string(&self.name);
+        writer.write_u8(self.difficulty.into());
+
+        writer.write_bytes(&[0; 16]);
+        writer.write_u64(0);
+        writer.write_bytes(&[0; 1 + 13 + 3]);
+        writer.write_f32(0.0);
+        writer.write_bytes(&[0; 1 + (3 * 4 + 4) * 2 + 13]);
+        writer.write_f32(if has(WorldEvent::Rain) { 1.0 } else { 0.0 });
+        writer.write_bytes(&[
+            bit(self.hardmode == Some(true), 0x10),
+            bit(has(WorldEvent::PumpkinMoon), 0x40) | bit(has(WorldEvent::FrostMoon), 0x80),
+            bit(has(WorldEvent::SlimeRain), 0x04),
+            bit(has(WorldEvent::Party), 0x80),
+            bit(has(WorldEvent::Sandstorm), 0x08) | bit(has(WorldEvent::OldOnesArmy), 0x10),
+            bit(has(WorldEvent::LanternNight), 0x02),
+            0,
+        ]);
+    }
+
+    fn read_payload(reader: &mut PayloadReader) -> io::Result<Self> {
+        let time = reader.read_i32()?;
+        let day_flags = reader.read_u8()?;
+        let moon_phase = reader.read_u8()?;
+        let width = reader.read_i16()? as u16;
+        let height = reader.read_i16()? as u16;
+        reader.skip(2 + 2 + 2 + 2)?; // spawn X/Y, surface and rock layer
+        reader.skip(4)?; // world ID
+        let name = reader.read_string()?;
+        let difficulty = Difficulty::from(reader.read_u8()?);
+
+        let mut events = Vec::new();
+        if day_flags & 0x02 != 0 {
+            events.push(WorldEvent::BloodMoon);
+        }
+        if day_flags & 0x04 != 0 {
+            events.push(WorldEvent::Eclipse);
+        }
+
+        // The rest shifts between releases, so a layout we don't know only costs us the flags
+        let hardmode = decode_world_flags(reader, &mut events).ok();
+
+        Ok(Self {
+            name,
+            width,
+            height,
+            time,
+            is_day: day_flags & 0x01 != 0,
+            moon_phase,
+            difficulty,
+            hardmode,
+            events,
+        })
+    }
+}
+
+/// Skips the background/cloud state and reads the world flag bytes, returning hardmode.
+fn decode_world_flags(reader: &mut PayloadReader, events: &mut Vec<WorldEvent>) -> io::Result<bool> {
+    reader.skip(16)?; // world unique ID
+    reader.skip(8)?; // world generator version
+    reader.skip(1)?; // moon type
+    reader.skip(13)?; // tree, corruption, jungle, ... backgrounds
+    reader.skip(3)?; // ice, jungle and hell back styles
+    reader.skip(4)?; // wind speed target
+    reader.skip(1)?; // cloud count
+    reader.skip(3 * 4 + 4)?; // tree X positions and styles
+    reader.skip(3 * 4 + 4)?; // cave back X positions and styles
+    reader.skip(13)?; // tree top variations
+    let max_raining = reader.read_f32()?;
+
+    let flags: [u8; 7] = [
+        reader.read_u8()?,
+        reader.read_u8()?,
+        reader.read_u8()?,
+        reader.read_u8()?,
+        reader.read_u8()?,
+        reader.read_u8()?,
+        reader.read_u8()?,
+    ];
+
+    let checks = [
+        (flags[1] & 0x40 != 0, WorldEvent::PumpkinMoon),
+        (flags[1] & 0x80 != 0, WorldEvent::FrostMoon),
+        (flags[2] & 0x04 != 0, WorldEvent::SlimeRain),
+        (flags[3] & 0x80 != 0, WorldEvent::Party),
+        (flags[4] & 0x08 != 0, WorldEvent::Sandstorm),
+        (flags[4] & 0x10 != 0, WorldEvent::OldOnesArmy),
+        (flags[5] & 0x02 != 0, WorldEvent::LanternNight),
+        (max_raining > 0.0, WorldEvent::Rain),
+    ];
+    events.extend(checks.into_iter().filter(|(active, _)| *active).map(|(_, event)| event));
+
+    Ok(flags[0] & 0x10 != 0)
+}
+
+impl From<u8> for Difficulty {
+    fn from(game_mode: u8) -> Self {
+        match game_mode {
+            0 => Difficulty::Classic,
+            1 => Difficulty::Expert,
+            2 => Difficulty::Master,
+            3 => Difficulty::Journey,
+            other => Difficulty::Unknown(other),
+        }
+    }
+}
+
+impl From<Difficulty> for u8 {
+    fn from(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Classic => 0,
+            Difficulty::Expert => 1,
+            Difficulty::Master => 2,
+            Difficulty::Journey => 3,
+            Difficulty::Unknown(mode) => mode,
+        }
+    }
+}
+
+impl fmt::Display for WorldSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldSize::Small => write!(f, "Small"),
+            WorldSize::Medium => write!(f, "Medium"),
+            WorldSize::Large => write!(f, "Large"),
+            WorldSize::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Classic => write!(f, "Classic"),
+            Difficulty::Expert => write!(f, "Expert"),
+            Difficulty::Master => write!(f, "Master"),
+            Difficulty::Journey => write!(f, "Journey"),
+            Difficulty::Unknown(mode) => write!(f, "Unknown ({})", mode),
+        }
+    }
+}
+
+impl fmt::Display for WorldEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WorldEvent::BloodMoon => "Blood Moon",
+            WorldEvent::Eclipse => "Solar Eclipse",
+            WorldEvent::PumpkinMoon => "Pumpkin Moon",
+            WorldEvent::FrostMoon => "Frost Moon",
+            WorldEvent::SlimeRain => "Slime Rain",
+            WorldEvent::Party => "Party",
+            WorldEvent::Sandstorm => "Sandstorm",
+            WorldEvent::OldOnesArmy => "Old One's Army",
+            WorldEvent::LanternNight => "Lantern Night",
+            WorldEvent::Rain => "Rain",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::decode_packet;
+
+    fn world() -> WorldInfo {
+        WorldInfo {
+            name: "Bananil".to_string(),
+            width: 6400,
+            height: 1800,
+            time: 27000,
+            is_day: true,
+            moon_phase: 3,
+            difficulty: Difficulty::Expert,
+            hardmode: Some(true),
+            events: vec![WorldEvent::BloodMoon, WorldEvent::PumpkinMoon, WorldEvent::Party, WorldEvent::Rain],
+        }
+    }
+
+    #[test]
+    fn world_info_round_trips() {
+        let frame = world().encode();
+        let (msg_type, payload, _) = decode_packet(&frame).unwrap().unwrap();
+        assert_eq!(msg_type, WORLD_INFO);
+        assert_eq!(WorldInfo::decode(payload).unwrap(), world());
+    }
+
+    #[test]
+    fn unknown_tail_layout_keeps_basic_fields() {
+        let frame = world().encode();
+        let (_, payload, _) = decode_packet(&frame).unwrap().unwrap();
+        // Cut right after the difficulty byte, as an older or newer layout might diverge there
+        let basic_len = 4 + 1 + 1 + 2 * 2 + 2 * 4 + 4 + 1 + "Bananil".len() + 1;
+        let decoded = WorldInfo::decode(&payload[..basic_len]).unwrap();
+
+        assert_eq!(decoded.name, "Bananil");
+        assert_eq!(decoded.size(), WorldSize::Medium);
+        assert_eq!(decoded.hardmode, None);
+        assert_eq!(decoded.events, vec![WorldEvent::BloodMoon]);
+    }
+
+    #[test]
+    fn clock_counts_from_dawn_and_dusk() {
+        assert_eq!(world().clock(), "12:00");
+        assert_eq!(WorldInfo { is_day: false, time: 0, ..world() }.clock(), "19:30");
+        assert_eq!(WorldInfo { is_day: false, time: 32400, ..world() }.clock(), "04:30");
+    }
+}
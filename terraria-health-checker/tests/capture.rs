@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use terraria_health_checker::protocol::{Message, SetUserSlot};
+use terraria_health_checker::{check_terraria_status, Capture, CheckOptions, ProbeOutcome};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("terraria-capture-{}-{}.pcap", name, std::process::id()))
+}
+
+/// TCP payloads and flags of every IPv4 packet in the pcap at `path`.
+fn tcp_packets(path: &PathBuf) -> Vec<(u8, Vec<u8>)> {
+    let bytes = std::fs::read(path).unwrap();
+    let mut packets = Vec::new();
+    let mut pos = 24;
+    while pos < bytes.len() {
+        let len = u32::from_le_bytes(bytes[pos + 8..pos + 12].try_into().unwrap()) as usize;
+        let packet = &bytes[pos + 16..pos + 16 + len];
+        packets.push((packet[20 + 13], packet[40..].to_vec()));
+        pos += 16 + len;
+    }
+    packets
+}
+
+#[tokio::test]
+async fn capture_records_the_handshake_bytes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let reply = SetUserSlot { player_slot: 3, check_bytes_in_client_loop: Some(false) }.encode();
+    let sent = reply.clone();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 64];
+        let _ = socket.read(&mut buf).await;
+        let _ = socket.write_all(&sent).await;
+    });
+
+    let path = temp_path("handshake");
+    let options = CheckOptions { capture: Some(Capture::create(&path).unwrap()), ..Default::default() };
+    let info = check_terraria_status("127.0.0.1", port, &options).await;
+    drop(options);
+
+    assert!(info.is_online);
+    let packets = tcp_packets(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(packets[0].0, TCP_SYN);
+    assert_eq!(packets[1].0, TCP_SYN | TCP_ACK);
+    // The ConnectRequest names the release, and the reply comes back byte for byte
+    assert!(String::from_utf8_lossy(&packets[3].1).contains("Terraria"));
+    let incoming: Vec<u8> = packets[4..].iter().flat_map(|(_, payload)| payload.clone()).collect();
+    assert_eq!(incoming, reply);
+}
+
+#[tokio::test]
+async fn capture_records_a_refused_connect() {
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+
+    let path = temp_path("refused");
+    let options = CheckOptions { capture: Some(Capture::create(&path).unwrap()), ..Default::default() };
+    let info = check_terraria_status("127.0.0.1", port, &options).await;
+    drop(options);
+
+    assert_eq!(info.outcome, ProbeOutcome::Refused);
+    let flags: Vec<u8> = tcp_packets(&path).into_iter().map(|(flags, _)| flags).collect();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(flags, vec![TCP_SYN, TCP_RST | TCP_ACK]);
+}
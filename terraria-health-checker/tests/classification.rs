@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use terraria_health_checker::mock::{MockReply, MockResponder};
+use terraria_health_checker::protocol::{self, NetworkText};
+use terraria_health_checker::{
+    check_terraria_status, discover_terraria_version, CheckOptions, Difficulty, DisconnectReason, HandshakeOutcome,
+    HealthCheck, ProbeOutcome, TerrariaCheck, WorldInfo, PROBE_PLAYER_NAME, TERRARIA_RELEASE,
+};
+
+fn world() -> WorldInfo {
+    WorldInfo {
+        name: "Bananil".to_string(),
+        width: 4200,
+        height: 1200,
+        time: 0,
+        is_day: true,
+        moon_phase: 0,
+        difficulty: Difficulty::Master,
+        hardmode: Some(false),
+        events: Vec::new(),
+    }
+}
+
+fn quick() -> CheckOptions {
+    CheckOptions { connect_timeout: Duration::from_millis(500), deadline: Duration::from_secs(1), ..Default::default() }
+}
+
+#[tokio::test]
+async fn accepted_handshake_is_online() {
+    let server = MockResponder::new(MockReply::Accept(3)).start().await.unwrap();
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert!(info.is_online);
+    assert_eq!(info.outcome, ProbeOutcome::Answered);
+    assert_eq!(info.handshake, Some(HandshakeOutcome::Accepted { player_slot: 3 }));
+    assert_eq!(info.server_version.and_then(|version| version.release), Some(TERRARIA_RELEASE));
+    assert_eq!(server.connect_releases(), vec![Some(TERRARIA_RELEASE)]);
+}
+
+#[tokio::test]
+async fn password_prompt_is_online() {
+    let server = MockResponder::new(MockReply::PasswordRequired).start().await.unwrap();
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert!(info.is_online);
+    assert_eq!(info.handshake, Some(HandshakeOutcome::PasswordRequired));
+}
+
+#[tokio::test]
+async fn disconnect_reasons_are_classified() {
+    let server = MockResponder::new(MockReply::Disconnect(NetworkText::key("CLI.ServerIsFull")))
+        .then(MockReply::Disconnect(NetworkText::literal("You are banned for 3 days")))
+        .then(MockReply::Disconnect(NetworkText::literal("Maintenance")))
+        .start()
+        .await
+        .unwrap();
+
+    let mut reasons = Vec::new();
+    for _ in 0..3 {
+        let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+        assert!(info.is_online);
+        reasons.push(info.handshake);
+    }
+
+    assert_eq!(
+        reasons,
+        vec![
+            Some(HandshakeOutcome::Rejected(DisconnectReason::ServerFull)),
+            Some(HandshakeOutcome::Rejected(DisconnectReason::Banned)),
+            Some(HandshakeOutcome::Rejected(DisconnectReason::Other("Maintenance".to_string()))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn version_hint_in_the_mismatch_reason_names_the_server_version() {
+    let reason = NetworkText::literal("You are not using the same version as this server (v1.4.4.8)");
+    let server = MockResponder::new(MockReply::Disconnect(reason)).start().await.unwrap();
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert_eq!(info.handshake, Some(HandshakeOutcome::Rejected(DisconnectReason::VersionMismatch)));
+    let version = info.server_version.unwrap();
+    assert_eq!((version.version.as_deref(), version.release), (Some("1.4.4.8"), Some(277)));
+}
+
+#[tokio::test]
+async fn stalled_server_is_offline_with_no_reply() {
+    let server = MockResponder::new(MockReply::Stall).start().await.unwrap();
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert!(!info.is_online);
+    assert_eq!(info.outcome, ProbeOutcome::Timeout);
+    assert_eq!(info.handshake, Some(HandshakeOutcome::NoReply));
+}
+
+#[tokio::test]
+async fn closed_connection_is_a_reset() {
+    let server = MockResponder::new(MockReply::Close).start().await.unwrap();
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert!(!info.is_online);
+    assert_eq!(info.outcome, ProbeOutcome::Reset);
+}
+
+#[tokio::test]
+async fn refused_connections_come_back() {
+    let server = MockResponder::new(MockReply::Accept(0)).start().await.unwrap();
+
+    server.refuse_connections(true);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let refused = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+    server.refuse_connections(false);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let back = check_terraria_status("127.0.0.1", server.port(), &quick()).await;
+
+    assert_eq!(refused.outcome, ProbeOutcome::Refused);
+    assert!(!refused.is_online);
+    assert!(back.is_online);
+}
+
+#[tokio::test]
+async fn deep_probe_reads_the_world() {
+    let server = MockResponder::new(MockReply::World(world())).start().await.unwrap();
+    let options = CheckOptions { deep: true, ..quick() };
+
+    let info = check_terraria_status("127.0.0.1", server.port(), &options).await;
+
+    assert_eq!(info.world, Some(world()));
+    let player = server.received().into_iter().find(|(msg_type, _)| *msg_type == protocol::PLAYER_INFO).unwrap();
+    assert!(String::from_utf8_lossy(&player.1).contains(PROBE_PLAYER_NAME));
+}
+
+#[tokio::test]
+async fn discovery_walks_the_releases_down_to_the_accepted_one() {
+    let server = MockResponder::new(MockReply::Accept(0)).release(275).start().await.unwrap();
+
+    let version = discover_terraria_version("127.0.0.1", server.port(), &quick()).await.unwrap().unwrap();
+
+    assert_eq!(version.release, Some(275));
+    assert_eq!(version.version.as_deref(), Some("1.4.4.6"));
+    assert_eq!(server.connect_releases(), vec![Some(279), Some(278), Some(277), Some(276), Some(275)]);
+}
+
+#[tokio::test]
+async fn health_check_reports_through_the_common_result() {
+    let accepting = MockResponder::new(MockReply::Accept(0)).start().await.unwrap();
+    let stalled = MockResponder::new(MockReply::Stall).start().await.unwrap();
+    let checks: Vec<Box<dyn HealthCheck>> = vec![
+        Box::new(TerrariaCheck::new("127.0.0.1", accepting.port(), quick())),
+        Box::new(TerrariaCheck::new("127.0.0.1", stalled.port(), quick())),
+        Box::new(TerrariaCheck::new("no-such-host.invalid", 7777, quick())),
+    ];
+
+    let mut results = Vec::new();
+    for check in &checks {
+        assert_eq!(check.name(), "Terraria");
+        results.push(check.check().await);
+    }
+
+    assert_eq!(results[0].status(), Some(true));
+    assert_eq!(results[0].version.as_deref(), Some("1.4.4.9"));
+    assert_eq!(results[1].status(), Some(false));
+    assert_eq!(results[1].failure.as_deref(), Some("Port open but no game reply (Timed out)"));
+    assert!(matches!(results[2].outcome, ProbeOutcome::DnsFailure(_)));
+    assert_eq!(results[2].status(), None);
+}
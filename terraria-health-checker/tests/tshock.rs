@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+
+use terraria_health_checker::protocol::{Message, SetUserSlot};
+use terraria_health_checker::{
+    check_tshock_status, fetch_tshock_status, CheckOptions, HandshakeOutcome, TShockConfig, TShockError,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const STATUS_BODY: &str = r#"{
+    "status": "200",
+    "name": "Bananil",
+    "serverversion": "v1.4.4.9",
+    "tshockversion": "5.2.0.0",
+    "port": 7777,
+    "playercount": 2,
+    "maxplayers": 12,
+    "world": "BananilWorld",
+    "uptime": "0.03:12:45",
+    "serverpassword": false,
+    "players": [
+        {"nickname": "Leo", "username": "leo", "group": "admin", "active": true, "state": 10, "team": 0},
+        {"nickname": "Nil", "username": "", "group": "guest", "active": true, "state": 10, "team": 0}
+    ]
+}"#;
+
+/// Serves `body` with `status_line` to every request and records the request lines.
+async fn mock_rest(status_line: &'static str, body: &'static str) -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]);
+            seen.lock().unwrap().push(request.lines().next().unwrap_or_default().to_string());
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status_line,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (port, requests)
+}
+
+/// Game port that accepts every ConnectRequest.
+async fn mock_game_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            let _ = socket.read(&mut buf).await;
+            let reply = SetUserSlot { player_slot: 0, check_bytes_in_client_loop: Some(false) }.encode();
+            let _ = socket.write_all(&reply).await;
+        }
+    });
+
+    port
+}
+
+/// A port with nothing listening on it.
+async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+fn config(rest_port: u16) -> TShockConfig {
+    TShockConfig { rest_port, token: "secret-token".to_string() }
+}
+
+#[tokio::test]
+async fn fetches_server_status_with_players() {
+    let (rest_port, requests) = mock_rest("200 OK", STATUS_BODY).await;
+
+    let status = fetch_tshock_status("127.0.0.1", &config(rest_port)).await.unwrap();
+
+    assert_eq!(status.server_name, "Bananil");
+    assert_eq!(status.world, "BananilWorld");
+    assert_eq!(status.player_count, 2);
+    assert_eq!(status.max_players, 12);
+    assert_eq!(status.players, vec!["Leo", "Nil"]);
+    assert_eq!(status.uptime, "0.03:12:45");
+    assert_eq!(status.server_version.as_deref(), Some("v1.4.4.9"));
+    assert!(!status.password_protected);
+
+    let request_line = requests.lock().unwrap()[0].clone();
+    assert!(request_line.starts_with("GET /v2/server/status?"));
+    assert!(request_line.contains("players=true"));
+    assert!(request_line.contains("token=secret-token"));
+}
+
+#[tokio::test]
+async fn rejected_token_is_unauthorized() {
+    let body = r#"{"status": "403", "error": "Not authorized. The specified API endpoint requires a token."}"#;
+    let (rest_port, _) = mock_rest("403 Forbidden", body).await;
+
+    let err = fetch_tshock_status("127.0.0.1", &config(rest_port)).await.unwrap_err();
+
+    assert!(matches!(err, TShockError::Unauthorized(msg) if msg.starts_with("Not authorized")));
+}
+
+#[tokio::test]
+async fn missing_rest_api_is_unavailable() {
+    let err = fetch_tshock_status("127.0.0.1", &config(closed_port().await)).await.unwrap_err();
+
+    assert!(matches!(err, TShockError::Unavailable(_)));
+}
+
+#[tokio::test]
+async fn check_combines_probe_and_rest_status() {
+    let game_port = mock_game_port().await;
+    let (rest_port, _) = mock_rest("200 OK", STATUS_BODY).await;
+
+    let info = check_tshock_status("127.0.0.1", game_port, &CheckOptions::default(), &config(rest_port)).await;
+
+    assert!(info.is_online);
+    assert_eq!(info.handshake, Some(HandshakeOutcome::Accepted { player_slot: 0 }));
+    assert_eq!(info.tshock.unwrap().player_count, 2);
+}
+
+#[tokio::test]
+async fn check_degrades_to_plain_probe_without_rest() {
+    let game_port = mock_game_port().await;
+
+    let info = check_tshock_status("127.0.0.1", game_port, &CheckOptions::default(), &config(closed_port().await)).await;
+
+    assert!(info.is_online);
+    assert!(info.tshock.is_none());
+}
+
+#[tokio::test]
+async fn check_uses_the_callers_probe_options() {
+    let game_port = mock_game_port().await;
+    let (rest_port, _) = mock_rest("200 OK", STATUS_BODY).await;
+    let options = CheckOptions { probe_all_addresses: true, ..Default::default() };
+
+    let info = check_tshock_status("127.0.0.1", game_port, &options, &config(rest_port)).await;
+
+    assert_eq!(info.addresses.len(), 1);
+    assert!(info.addresses[0].is_online);
+    assert!(info.tshock.is_some());
+}